@@ -0,0 +1,246 @@
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote, quote_spanned};
+use syn::parse::{Parse, ParseStream};
+
+pub struct MicroCall {
+    mode: syn::Ident,
+    expr: syn::Expr,
+}
+
+impl Parse for MicroCall {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mode = input.parse()?;
+        let expr = input.parse()?;
+        Ok(MicroCall { mode, expr })
+    }
+}
+
+pub fn micro_call_impl(micro_call: MicroCall) -> TokenStream {
+    match expand(micro_call) {
+        Ok(output) => output,
+        Err(e) => e.to_compile_error(),
+    }
+}
+
+fn expand(micro_call: MicroCall) -> syn::Result<TokenStream> {
+    // Get the name of micro_test crate
+    let micro_test_crate_string = match proc_macro_crate::crate_name("micro_test") {
+        Ok(founded_crate) => match founded_crate {
+            proc_macro_crate::FoundCrate::Itself => String::from("micro_test"),
+            proc_macro_crate::FoundCrate::Name(name_string) => name_string,
+        },
+        Err(e) => panic!("Cannot find micro_test crate: {}", e),
+    };
+    let micro_test_crate: syn::PathSegment = syn::parse_str(&micro_test_crate_string).unwrap();
+
+    let MicroCall { mode, expr } = micro_call;
+    if !matches!(expr, syn::Expr::Call(_) | syn::Expr::MethodCall(_)) {
+        return Err(syn::Error::new_spanned(
+            expr,
+            "expected a function call, a method call or a closure call",
+        ));
+    }
+
+    match mode.to_string().as_str() {
+        "result" => Ok(quote!(#expr)),
+        "unwrap" => Ok(quote!((#expr).unwrap())),
+        "relay" => Ok(relay(
+            &micro_test_crate,
+            &expr,
+            quote!(::core::option::Option::None),
+            Span::call_site(),
+        )),
+        "relay_debug" => Ok(relay_debug(&micro_test_crate, expr)),
+        _ => Err(syn::Error::new_spanned(
+            mode,
+            "expected one of `relay`, `relay_debug`, `result` or `unwrap`",
+        )),
+    }
+}
+
+/// Binds the arguments first, so that each is evaluated once but could be
+/// rendered before being moved into the call.
+fn relay_debug(micro_test_crate: &syn::PathSegment, mut expr: syn::Expr) -> TokenStream {
+    let args = match &mut expr {
+        syn::Expr::Call(call_expr) => &mut call_expr.args,
+        syn::Expr::MethodCall(method_call_expr) => &mut method_call_expr.args,
+        _ => unreachable!(),
+    };
+    let values: Vec<syn::Expr> = args.iter().cloned().collect();
+    let bindings: Vec<syn::Ident> = (0..values.len())
+        .map(|i| format_ident!("__micro_test_arg_{}", i, span = Span::mixed_site()))
+        .collect();
+    for (arg, binding) in args.iter_mut().zip(&bindings) {
+        *arg = syn::parse_quote!(#binding);
+    }
+    let args_ident = syn::Ident::new("__micro_test_args", Span::mixed_site());
+    let call = relay(
+        micro_test_crate,
+        &expr,
+        quote!(::core::option::Option::Some(#args_ident)),
+        Span::call_site(),
+    );
+    quote! {
+        match (#(#values,)*) {
+            (#(#bindings,)*) => {
+                let #args_ident = #micro_test_crate::backtrace::FuncArgs::from_debug(&[
+                    #(&#bindings as &dyn ::core::fmt::Debug),*
+                ]);
+                #call
+            }
+        }
+    }
+}
+
+/// Relays the call stack returned by `expr` with a frame pushed onto it,
+/// whose location is the one of `location`.
+pub fn relay(
+    micro_test_crate: &syn::PathSegment,
+    expr: &syn::Expr,
+    args: TokenStream,
+    location: Span,
+) -> TokenStream {
+    // The type of the receiver is inferred from a branch never taken. A
+    // receiver which is not a place, e.g. `make(v)` in `make(v).read()`, is
+    // bound first, as inferring from it would move `v` a second time.
+    let mut expr = expr.clone();
+    let (name, infer_receiver, receiver) = match &mut expr {
+        syn::Expr::MethodCall(method_call_expr) => {
+            let bind_receiver = if is_place(&method_call_expr.receiver) {
+                quote!()
+            } else {
+                let value = syn::Ident::new("__micro_test_receiver_value", Span::mixed_site());
+                let receiver = &method_call_expr.receiver;
+                let bind_receiver = quote! {
+                    #[allow(unused_mut)]
+                    let mut #value = #receiver;
+                };
+                *method_call_expr.receiver = syn::parse_quote!(#value);
+                bind_receiver
+            };
+            let receiver = &method_call_expr.receiver;
+            (
+                method_call_expr.method.to_string(),
+                quote! {
+                    #bind_receiver
+                    let mut __micro_test_receiver = ::core::marker::PhantomData;
+                    #[allow(unreachable_code)]
+                    if false {
+                        #micro_test_crate::backtrace::infer_receiver_type(
+                            &mut __micro_test_receiver,
+                            &#receiver,
+                        );
+                    }
+                },
+                quote! {
+                    ::core::option::Option::Some(
+                        #micro_test_crate::backtrace::receiver_type_name(__micro_test_receiver)
+                    )
+                },
+            )
+        }
+        // `Self::method(...)` is named after the type `Self` stands for, as
+        // method calls are.
+        syn::Expr::Call(call_expr) => match self_path(&call_expr.func) {
+            Some(rest) => (
+                rest,
+                quote!(),
+                quote! {
+                    ::core::option::Option::Some(
+                        #micro_test_crate::backtrace::receiver_type_name(
+                            ::core::marker::PhantomData::<*const Self>,
+                        )
+                    )
+                },
+            ),
+            None => (
+                callee_name(&call_expr.func),
+                quote!(),
+                quote!(::core::option::Option::None),
+            ),
+        },
+        _ => (callee_name(&expr), quote!(), quote!(::core::option::Option::None)),
+    };
+    let file = quote_spanned!(location=> ::core::file!());
+    let line = quote_spanned!(location=> ::core::line!());
+    let column = quote_spanned!(location=> ::core::column!());
+    quote! {
+        {
+            #infer_receiver
+            match #expr {
+                ::core::result::Result::Ok(ret) => ret,
+                ::core::result::Result::Err(mut call_stack) => {
+                    call_stack.push(#micro_test_crate::backtrace::FuncCall {
+                        name: #name,
+                        file: #file,
+                        line: #line,
+                        column: #column,
+                        receiver: #receiver,
+                        args: #args,
+                    });
+                    return ::core::result::Result::Err(call_stack);
+                }
+            }
+        }
+    }
+}
+
+/// Checks whether `expr` is a place, which could be borrowed to infer its
+/// type without being moved.
+fn is_place(expr: &syn::Expr) -> bool {
+    match expr {
+        syn::Expr::Path(_) => true,
+        syn::Expr::Field(field_expr) => is_place(&field_expr.base),
+        syn::Expr::Index(index_expr) => is_place(&index_expr.expr),
+        syn::Expr::Unary(unary_expr) => {
+            matches!(unary_expr.op, syn::UnOp::Deref(_)) && is_place(&unary_expr.expr)
+        }
+        syn::Expr::Paren(paren_expr) => is_place(&paren_expr.expr),
+        syn::Expr::Group(group_expr) => is_place(&group_expr.expr),
+        _ => false,
+    }
+}
+
+/// Returns the rest of a path starting with `Self`, e.g. `reset` for
+/// `Self::reset`.
+fn self_path(func: &syn::Expr) -> Option<String> {
+    match func {
+        syn::Expr::Path(path_expr) if path_expr.qself.is_none() => {
+            let mut segments = path_expr.path.segments.iter().map(|segment| segment.ident.to_string());
+            if path_expr.path.segments.len() < 2 || segments.next()? != "Self" {
+                return None;
+            }
+            Some(segments.collect::<Vec<_>>().join("::"))
+        }
+        syn::Expr::Paren(paren_expr) => self_path(&paren_expr.expr),
+        syn::Expr::Group(group_expr) => self_path(&group_expr.expr),
+        _ => None,
+    }
+}
+
+/// Names a callee after its path without generic arguments, e.g.
+/// `driver::init`, or after the field holding it, e.g. `self.callback`.
+fn callee_name(func: &syn::Expr) -> String {
+    match func {
+        syn::Expr::Path(path_expr) => {
+            let segments: Vec<String> = path_expr
+                .path
+                .segments
+                .iter()
+                .map(|segment| segment.ident.to_string())
+                .collect();
+            segments.join("::")
+        }
+        syn::Expr::Field(field_expr) => {
+            let member = match &field_expr.member {
+                syn::Member::Named(ident) => ident.to_string(),
+                syn::Member::Unnamed(index) => index.index.to_string(),
+            };
+            format!("{}.{}", callee_name(&field_expr.base), member)
+        }
+        syn::Expr::Paren(paren_expr) => callee_name(&paren_expr.expr),
+        syn::Expr::Group(group_expr) => callee_name(&group_expr.expr),
+        syn::Expr::Closure(_) => String::from("{closure}"),
+        _ => String::from("{callee}"),
+    }
+}
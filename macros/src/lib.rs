@@ -4,10 +4,12 @@ extern crate proc_macro2;
 extern crate quote;
 extern crate syn;
 
+mod call;
 mod panic;
 mod test_case;
 
 use proc_macro2::TokenStream;
+use crate::call::micro_call_impl;
 use crate::panic::micro_panic_relay_impl;
 use crate::panic::micro_panic_receiver_impl;
 use crate::test_case::micro_test_case_impl;
@@ -16,7 +18,10 @@ use crate::test_case::micro_test_case_impl;
 ///
 /// # Usage
 ///
-/// This attribute should be used just like `#[test_case]` attribute.
+/// This attribute should be used just like `#[test_case]` attribute. The
+/// `#[test_case]` registration is only emitted with feature
+/// `custom_test_frameworks`, which is on by default in `micro_test`; without
+/// it, test functions should be collected with `micro_test::tests!`.
 ///
 /// ```
 /// # #![feature(custom_test_frameworks)]
@@ -40,18 +45,74 @@ use crate::test_case::micro_test_case_impl;
 /// # fn main() { }
 /// ```
 ///
+/// A test could be ignored with `#[micro_ignore]`, optionally with a reason.
+/// Ignored tests still report their metadata, with `ignore` set, but their
+/// body is not executed.
+/// ```
+/// # #![feature(custom_test_frameworks)]
+/// # #![test_runner(test_runner)]
+/// # use micro_test::micro_test_case;
+/// #[micro_test_case(target = "flash", feature = "erase")]
+/// #[micro_ignore = "needs external flash"]
+/// fn ignored_test_function() { }
+/// # fn main() { }
+/// ```
+///
+/// A test expected to fail could be marked with `#[micro_should_fail]`. It
+/// passes only if a `micro_panic!` fires in it, and, when `expected` is given,
+/// the panic message contains the expected substring.
+/// ```
+/// # #![feature(custom_test_frameworks)]
+/// # #![test_runner(test_runner)]
+/// # use micro_test::micro_assert;
+/// # use micro_test::micro_test_case;
+/// #[micro_test_case(target = "driver", feature = "bounds check")]
+/// #[micro_should_fail(expected = "overflow")]
+/// fn should_fail_test_function() {
+///     micro_assert!(false, "overflow");
+/// }
+/// # fn main() { }
+/// ```
+///
+/// A test function could take inputs if it is given rows of arguments with
+/// `#[micro_case(...)]`. Each row becomes a test function of its own, named
+/// `<function>_case_<index>`, whose metadata has the arguments appended to
+/// `feature`, e.g. `crc (table: 0x01, 0x1021)`.
+/// ```
+/// # #![feature(custom_test_frameworks)]
+/// # #![test_runner(test_runner)]
+/// # use micro_test::micro_assert_eq;
+/// # use micro_test::micro_test_case;
+/// # fn crc(input: u8) -> u16 { 0 }
+/// #[micro_test_case(target = "crc", feature = "table")]
+/// #[micro_case(0x00, 0x0000)]
+/// #[micro_case(0x01, 0x1021)]
+/// fn crc_table(input: u8, expected: u16) {
+///     micro_assert_eq!(crc(input), expected);
+/// }
+/// # fn main() { }
+/// ```
+///
 /// # Explanations
 ///
 /// This procedural macro turns the test function into
-/// ```
+/// ```ignore
+/// #[micro_test::panic::micro_panic_relay]
 /// #[test_case]
 /// fn test_function() {
-///     micro_test::call_user_processor_prepare(Metadata {
+///     let metadata = micro_test::test::Metadata {
 ///         target: "test target",
-///         feature: "feature tested"
-///     });
-///     { /* original function body */ }
-///     micro_test::call_user_processor_settle(Ok(()));
+///         feature: Some("feature tested"),
+///         name: "test_function",
+///         file: file!(),
+///         line: line!(),
+///         module_path: module_path!(),
+///         ..
+///     };
+///     if micro_test::test::filter_metadata(&metadata) {
+///         micro_test::test::report_metadata(&metadata);
+///         if !metadata.ignore { /* original function body */ }
+///     }
 /// }
 /// ```
 #[proc_macro_attribute]
@@ -64,6 +125,21 @@ pub fn micro_test_case(
     proc_macro::TokenStream::from(output)
 }
 
+/// Turns a function into one relaying `micro_panic!` to its caller
+///
+/// Under `cfg(test)`, the function returns `Result<T, CallStack>` instead of
+/// `T`, and should be called with `micro_call!`. Its body and every `return`
+/// in it, except those in closures, async blocks and nested functions, are
+/// wrapped in `Ok(..)`. Inside it, `?` relays the call stack returned by
+/// another relay function just like `micro_call!(relay ...)`, pushing a frame
+/// located at the `?`.
+/// ```ignore
+/// #[micro_panic_relay]
+/// fn init(uart: &mut Uart) {
+///     uart.reset()?;
+///     uart.write_reg(CTRL, ENABLE)?;
+/// }
+/// ```
 #[proc_macro_attribute]
 pub fn micro_panic_relay(
     _attr: proc_macro::TokenStream,
@@ -81,3 +157,31 @@ pub fn micro_panic_receiver(
     let item_fn = syn::parse_macro_input!(item as syn::ItemFn);
     proc_macro::TokenStream::from(micro_panic_receiver_impl(item_fn))
 }
+
+/// Calls a `#[micro_panic_relay]` function
+///
+/// The call could be a function call, e.g. `driver::init(cfg)`,
+/// `Self::reset()` or `parse::<u8>(byte)`, a method call, e.g.
+/// `self.read_reg(x)`, or a call of a closure or a function pointer, e.g.
+/// `(self.callback)(x)`.
+///
+/// - `relay` returns the call stack to the caller, which must be a
+///   `#[micro_panic_relay]` function itself, with the call pushed onto it.
+///   Methods are named after the type of their receiver, e.g.
+///   `Uart::read_reg`.
+/// - `relay_debug` does the same, and also records the Debug renderings of
+///   the arguments, which must implement `Debug` then. The arguments are
+///   evaluated before the receiver.
+/// - `result` evaluates to the `Result` returned by the call.
+/// - `unwrap` unwraps the `Result` returned by the call.
+///
+/// ```ignore
+/// micro_call!(relay self.read_reg(STATUS));
+/// micro_call!(relay_debug driver::init(cfg));
+/// micro_call!(unwrap Self::reset());
+/// ```
+#[proc_macro]
+pub fn micro_call(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let micro_call = syn::parse_macro_input!(input as crate::call::MicroCall);
+    proc_macro::TokenStream::from(micro_call_impl(micro_call))
+}
@@ -1,5 +1,9 @@
-use proc_macro2::TokenStream;
+use proc_macro2::{Span, TokenStream};
 use quote::quote;
+use syn::spanned::Spanned;
+use syn::visit_mut::{self, VisitMut};
+
+use crate::call::relay;
 
 pub fn micro_panic_receiver_impl(item_fn: syn::ItemFn) -> TokenStream {
     // Get the name of micro_test crate
@@ -29,7 +33,7 @@ pub fn micro_panic_receiver_impl(item_fn: syn::ItemFn) -> TokenStream {
         vis: fn_vis,
         sig: signature,
         block: std::boxed::Box::new(syn::parse_quote! { {
-            let #backtrace_ident = #backtrace_type::new();
+            let #backtrace_ident = <#backtrace_type>::new();
             #block
         } })
     };
@@ -75,7 +79,23 @@ pub fn micro_panic_relay_impl(item_fn: syn::ItemFn) -> TokenStream {
         }
     };
     let mut block = item_fn.block.clone();
-    transform_block(&mut block);
+    RelayRewrite {
+        micro_test_crate: &micro_test_crate,
+        in_try_block: false,
+    }
+    .visit_block_mut(&mut block);
+    // The body is wrapped as a whole, so that its value is returned wherever
+    // it comes from, e.g. a nested block, an `if let` or a labeled `break`.
+    // It is bound first, as braces around a call argument are linted.
+    let value = syn::Ident::new("__micro_test_value", Span::mixed_site());
+    let block = syn::parse_quote! {
+        {
+            #[allow(unreachable_code, clippy::diverging_sub_expression)]
+            let #value = #block;
+            #[allow(unreachable_code)]
+            return ::core::result::Result::Ok(#value);
+        }
+    };
     let new_fn = syn::ItemFn {
         attrs,
         vis: fn_vis,
@@ -90,76 +110,51 @@ pub fn micro_panic_relay_impl(item_fn: syn::ItemFn) -> TokenStream {
     }
 }
 
-fn transform_block(block: &mut syn::Block) {
-    fn transform_expr(expr: &mut syn::Expr) {
-        use syn::Expr::*;
-        match expr {
-            If(if_expr) => {
-                transform_block(&mut if_expr.then_branch);
-                match if_expr.else_branch.clone() {
-                    Some((_, mut else_branch_expr)) => transform_expr(&mut else_branch_expr),
-                    None => (),
-                }
-            },
-            ForLoop(for_loop_expr) => transform_block(&mut for_loop_expr.body),
-            Loop(loop_expr) => transform_block(&mut loop_expr.body),
-            While(while_expr) => transform_block(&mut while_expr.body),
-            Block(block_expr) => transform_block(&mut block_expr.block),
-            Match(match_expr) => {
-                let arms = &mut match_expr.arms;
-                for arm in arms {
-                    transform_expr(&mut *arm.body);
-                }
-            },
-            Return(return_expr) => {
-                let mut r_expr = return_expr.expr.clone();
-                return_expr.expr = match &mut r_expr {
-                    Some(boxed_expr) => Some(std::boxed::Box::new(syn::parse_quote! {
-                        ::core::result::Result::Ok(#boxed_expr)
-                    })),
-                    None => Some(std::boxed::Box::new(syn::parse_quote! {
-                        ::core::result::Result::Ok(())
-                    }))
-                }
-            },
-            _ => (),
-        }
-    }
-
-    fn transform_stmt(stmt: &mut syn::Stmt) {
-        match stmt {
-            syn::Stmt::Expr(expr) => transform_expr(expr),
-            syn::Stmt::Semi(semi_expr, _) => transform_expr(semi_expr),
-            _ => (),
-        }
-    }
+/// Rewrites the body of a relay function
+///
+/// - `return expr` is turned into `return Ok(expr)`.
+/// - `expr?` is turned into a relay of the call stack returned by `expr`, as
+///   `micro_call!(relay expr)` does, so that a frame is pushed onto it.
+///
+/// Closures, async blocks and nested items are left untouched, as `return`
+/// and `?` in them do not return from the function. Neither is `?` in try
+/// blocks. Macro invocations other than `micro_call!` are opaque, so `return`
+/// should not be passed to them.
+struct RelayRewrite<'a> {
+    micro_test_crate: &'a syn::PathSegment,
+    in_try_block: bool,
+}
 
-    let statements = &mut block.stmts;
-    if let Some((last_stmt, others)) = statements.split_last_mut() {
-        for statement in others {
-            transform_stmt(statement);
-        }
-        match last_stmt {
-            syn::Stmt::Expr(last_expr) => {
-                *last_expr = syn::parse_quote! {
-                    ::core::result::Result::Ok(#last_expr)
-                }
-            },
-            _ => {
-                statements.push(syn::parse_quote! {
-                    #[allow(unreachable_code)]
-                    {
-                        return ::core::result::Result::Ok(());
-                    }
+impl VisitMut for RelayRewrite<'_> {
+    fn visit_expr_mut(&mut self, expr: &mut syn::Expr) {
+        match expr {
+            syn::Expr::Closure(_) | syn::Expr::Async(_) => (),
+            syn::Expr::TryBlock(try_block_expr) => {
+                let in_try_block = std::mem::replace(&mut self.in_try_block, true);
+                self.visit_block_mut(&mut try_block_expr.block);
+                self.in_try_block = in_try_block;
+            }
+            syn::Expr::Return(return_expr) => {
+                visit_mut::visit_expr_return_mut(self, return_expr);
+                return_expr.expr = Some(match return_expr.expr.take() {
+                    Some(value) => syn::parse_quote!(::core::result::Result::Ok(#value)),
+                    None => syn::parse_quote!(::core::result::Result::Ok(())),
                 });
             }
-        }
-    } else {
-        statements.push(syn::parse_quote! {
-            #[allow(unreachable_code)]
-            {
-                return ::core::result::Result::Ok(());
+            syn::Expr::Try(try_expr) if !self.in_try_block => {
+                self.visit_expr_mut(&mut try_expr.expr);
+                let location = try_expr.question_token.span();
+                let relayed = relay(
+                    self.micro_test_crate,
+                    &try_expr.expr,
+                    quote!(::core::option::Option::None),
+                    location,
+                );
+                *expr = syn::parse_quote!(#relayed);
             }
-        });
+            _ => visit_mut::visit_expr_mut(self, expr),
+        }
     }
+
+    fn visit_item_mut(&mut self, _item: &mut syn::Item) {}
 }
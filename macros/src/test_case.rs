@@ -1,6 +1,7 @@
 use darling::FromMeta;
 use proc_macro2::TokenStream;
 use quote::quote;
+use syn::punctuated::Punctuated;
 
 #[derive(FromMeta)]
 struct PathTargetFeatureAttrArgs {
@@ -67,6 +68,12 @@ macro_rules! attr_fallback {
     }
 }
 
+#[derive(FromMeta)]
+struct ShouldFailAttrArgs {
+    #[darling(default)]
+    pub expected: Option<String>,
+}
+
 pub struct ProcMacroAttrArgs {
     pub path: bool,
     pub target: Option<String>,
@@ -88,20 +95,49 @@ pub fn micro_test_case_impl(attr_args: Vec<syn::NestedMeta>, item: TokenStream)
 
     let mut input = syn::parse2::<syn::ItemFn>(item).unwrap();
 
-    let mut is_ignored = true;
-    let attrs = input.attrs.clone();
-    for attr in attrs {
-        if attr.path.segments.len() == 1 {
-            if attr.path.segments.last().unwrap().ident.to_string() == "micro_ignore" {
-                is_ignored = true;
+    // Find and strip `#[micro_ignore]` / `#[micro_ignore = "reason"]` and
+    // `#[micro_should_fail]` / `#[micro_should_fail(expected = "message")]`
+    let mut ignore = false;
+    let mut ignore_message: Option<String> = None;
+    let mut should_fail = false;
+    let mut expected: Option<String> = None;
+    // Rows of `#[micro_case(arg, ...)]`
+    let mut cases: Vec<Punctuated<syn::Expr, syn::Token![,]>> = Vec::new();
+    input.attrs.retain(|attr| {
+        if is_micro_attr(attr, "micro_ignore", &micro_test_crate_string) {
+            ignore = true;
+            match attr.parse_meta() {
+                Ok(syn::Meta::Path(_)) => (),
+                Ok(syn::Meta::NameValue(syn::MetaNameValue { lit: syn::Lit::Str(reason), .. })) => {
+                    ignore_message = Some(reason.value());
+                }
+                _ => panic!("#[micro_ignore] should be either `#[micro_ignore]` or `#[micro_ignore = \"reason\"]`"),
             }
-        } else if attr.path.segments.len() == 2 {
-            if attr.path.segments.last().unwrap().ident.to_string() == "micro_ignore" &&
-                attr.path.segments.first().unwrap().ident.to_string() == micro_test_crate_string {
-                is_ignored = true;
+            false
+        } else if is_micro_attr(attr, "micro_should_fail", &micro_test_crate_string) {
+            should_fail = true;
+            match attr.parse_meta() {
+                Ok(syn::Meta::Path(_)) => (),
+                Ok(syn::Meta::List(list)) => {
+                    let nested: Vec<syn::NestedMeta> = list.nested.into_iter().collect();
+                    match ShouldFailAttrArgs::from_list(&nested) {
+                        Ok(args) => expected = args.expected,
+                        Err(e) => panic!("#[micro_should_fail] has invalid arguments: {}", e),
+                    }
+                }
+                _ => panic!("#[micro_should_fail] should be either `#[micro_should_fail]` or `#[micro_should_fail(expected = \"message\")]`"),
             }
+            false
+        } else if is_micro_attr(attr, "micro_case", &micro_test_crate_string) {
+            match attr.parse_args_with(Punctuated::parse_terminated) {
+                Ok(args) => cases.push(args),
+                Err(e) => panic!("#[micro_case] should be a list of arguments: {}", e),
+            }
+            false
+        } else {
+            true
         }
-    }
+    });
 
     // Process the function signature
     let signature = input.sig.clone();
@@ -120,7 +156,16 @@ pub fn micro_test_case_impl(attr_args: Vec<syn::NestedMeta>, item: TokenStream)
             panic!("#[micro_test_case] test function should not have return type")
         }
     }
-    assert_eq!(signature.inputs.len(), 0, "#[micro_test_case] test function should not have inputs");
+    if cases.is_empty() {
+        assert_eq!(signature.inputs.len(), 0, "#[micro_test_case] test function should not have inputs without #[micro_case]");
+    }
+    for case in &cases {
+        assert_eq!(
+            case.len(),
+            signature.inputs.len(),
+            "#[micro_case] should have as many arguments as the test function has inputs"
+        );
+    }
     let function_name = signature.ident.clone();
 
     // Set default values for attributes.
@@ -128,9 +173,9 @@ pub fn micro_test_case_impl(attr_args: Vec<syn::NestedMeta>, item: TokenStream)
         Some(target) => if attr_args.path { "::".to_owned() + &target } else { target },
         None => function_name.to_string(),
     };
-    let feature: TokenStream = match attr_args.feature {
+    let feature: TokenStream = match &attr_args.feature {
         Some(feature) => {
-            let some_token = "Some(\"".to_owned() + &feature + "\")";
+            let some_token = "Some(\"".to_owned() + feature + "\")";
             some_token.parse().unwrap()
         }
         None => quote!(None).into(),
@@ -143,39 +188,116 @@ pub fn micro_test_case_impl(attr_args: Vec<syn::NestedMeta>, item: TokenStream)
     let micro_test_crate = syn::Ident::from_string(&micro_test_crate_string).unwrap();
     let metadata_processor = syn::Ident::from_string(METADATA_PROCESSOR_NAME).unwrap();
 
-    input.block = if attr_args.path {
-        syn::parse_quote! {
-            {
-                #micro_test_crate::test::#metadata_processor(&#micro_test_crate::test::Metadata {
-                    target: ::core::concat!(::core::module_path!(), #target),
-                    feature: #feature,
-                });
-                #block
-            }
+    let target = if attr_args.path {
+        quote!(::core::concat!(::core::module_path!(), #target))
+    } else {
+        quote!(#target)
+    };
+    let ignore_message = match ignore_message {
+        Some(message) => quote!(Some(#message)),
+        None => quote!(None),
+    };
+    let expected = match expected {
+        Some(expected) => quote!(Some(#expected)),
+        None => quote!(None),
+    };
+    // `file!()` and `line!()` take the span of the function name, so that they
+    // point at the test function instead of the attribute.
+    let file = quote::quote_spanned!(function_name.span()=> ::core::file!());
+    let line = quote::quote_spanned!(function_name.span()=> ::core::line!());
+    let metadata = |feature: TokenStream, name: &syn::Ident| quote! {
+        #micro_test_crate::test::Metadata {
+            target: #target,
+            feature: #feature,
+            name: ::core::stringify!(#name),
+            file: #file,
+            line: #line,
+            module_path: ::core::module_path!(),
+            ignore: #ignore,
+            ignore_message: #ignore_message,
+            should_fail: #should_fail,
+            expected: #expected,
         }
+    };
+    let registration = if cfg!(feature = "custom_test_frameworks") {
+        quote!(#[test_case])
     } else {
-        syn::parse_quote! {
+        quote!()
+    };
+
+    if cases.is_empty() {
+        let metadata = metadata(feature, &function_name);
+        // The body is kept as a relay function shadowing the test function,
+        // so that a `return` in it cannot skip the check of expectations.
+        input.block = syn::parse_quote! {
             {
-                #micro_test_crate::test::#metadata_processor(&#micro_test_crate::test::Metadata {
-                    target: #target,
-                    feature: #feature,
-                });
-                #block
+                #[#micro_test_crate::panic::micro_panic_relay]
+                fn #function_name() #block
+                let __micro_test_metadata = #metadata;
+                if #micro_test_crate::test::filter_metadata(&__micro_test_metadata) {
+                    #micro_test_crate::test::#metadata_processor(&__micro_test_metadata);
+                    let __micro_test_expectation_failures = #micro_test_crate::panic::expectation_failures();
+                    if !__micro_test_metadata.ignore {
+                        if let ::core::result::Result::Err(call_stack) = #function_name() {
+                            #micro_test_crate::micro_panic!(@fail call_stack);
+                        }
+                    }
+                    if #micro_test_crate::panic::expectation_failures() != __micro_test_expectation_failures {
+                        #micro_test_crate::micro_panic!(@fail);
+                    }
+                }
             }
-        }
-    };
-    //input.block = Box::new(syn::parse2::<syn::Block>(new_block).unwrap());
-    if is_ignored {
-        quote! {
+        };
+        return quote! {
             #[#micro_test_crate::panic::micro_panic_relay]
-            #[test_case]
+            #registration
             #input
-        }
-    } else {
+        };
+    }
+
+    // Every row of arguments becomes a test function calling the original
+    // function, which is kept as a relay function.
+    let fn_vis = &input.vis;
+    let case_feature = &attr_args.feature;
+    let cases = cases.iter().enumerate().map(|(i, args)| {
+        let case_name = quote::format_ident!("{}_case_{}", function_name, i);
+        let feature = match case_feature {
+            Some(feature) => quote!(Some(::core::concat!(#feature, ": ", ::core::stringify!(#args)))),
+            None => quote!(Some(::core::stringify!(#args))),
+        };
+        let metadata = metadata(feature, &case_name);
         quote! {
             #[#micro_test_crate::panic::micro_panic_relay]
-            #input
+            #registration
+            #fn_vis fn #case_name() {
+                let __micro_test_metadata = #metadata;
+                if #micro_test_crate::test::filter_metadata(&__micro_test_metadata) {
+                    #micro_test_crate::test::#metadata_processor(&__micro_test_metadata);
+                    let __micro_test_expectation_failures = #micro_test_crate::panic::expectation_failures();
+                    if !__micro_test_metadata.ignore {
+                        #micro_test_crate::micro_call!(relay #function_name(#args));
+                    }
+                    if #micro_test_crate::panic::expectation_failures() != __micro_test_expectation_failures {
+                        #micro_test_crate::micro_panic!(@fail);
+                    }
+                }
+            }
         }
+    });
+    quote! {
+        #[#micro_test_crate::panic::micro_panic_relay]
+        #input
+        #(#cases)*
+    }
+}
+
+/// Checks whether `attr` is `#[name]` or `#[micro_test::name]`.
+fn is_micro_attr(attr: &syn::Attribute, name: &str, micro_test_crate: &str) -> bool {
+    let segments = &attr.path.segments;
+    match segments.len() {
+        1 => segments[0].ident == name,
+        2 => segments[0].ident == micro_test_crate && segments[1].ident == name,
+        _ => false,
     }
 }
 
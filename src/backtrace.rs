@@ -1,32 +1,493 @@
-extern crate alloc;
+use core::fmt::{Debug, Display, Formatter, Result as FmtResult, Write};
+use core::marker::PhantomData;
+use core::panic::Location;
 
-use alloc::vec::Vec;
+use crate::panic::PanicMessage;
 
+/// The default depth of [`CallStack`](struct.CallStack.html), which is used
+/// by `#[micro_panic_relay]` functions.
+pub const DEFAULT_CALL_STACK_DEPTH: usize = 16;
+
+/// The capacity in bytes of [`FuncArgs`](struct.FuncArgs.html).
+pub const FUNC_ARGS_CAPACITY: usize = 64;
+
+/// The functions a `micro_panic!` has been relayed through
+///
+/// Frames are pushed into a fixed-capacity array, so relaying works on targets
+/// without a global allocator. Frames beyond the capacity `N` are dropped, and
+/// the call stack is marked as truncated.
+///
+/// A call stack started by `micro_panic!` also holds the panic message and
+/// location, so that the runner gets them along with the frames once the test
+/// has returned.
 #[derive(Clone, Debug)]
-pub struct CallStack {
-    pub calls: Vec<FuncCall>
+pub struct CallStack<const N: usize = DEFAULT_CALL_STACK_DEPTH> {
+    calls: [FuncCall; N],
+    len: usize,
+    truncated: bool,
+    message: Option<PanicMessage>,
+    location: Option<PanicLocation>,
+}
+
+impl<const N: usize> Default for CallStack<N> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
-impl CallStack {
-    pub fn new() -> Self {
+impl<const N: usize> CallStack<N> {
+    pub const fn new() -> Self {
+        Self {
+            calls: [FuncCall::EMPTY; N],
+            len: 0,
+            truncated: false,
+            message: None,
+            location: None,
+        }
+    }
+
+    /// Creates an empty call stack holding the message and location of a
+    /// panic.
+    pub const fn from_panic(message: PanicMessage, location: Option<PanicLocation>) -> Self {
         Self {
-            calls: Vec::<FuncCall>::new(),
+            calls: [FuncCall::EMPTY; N],
+            len: 0,
+            truncated: false,
+            message: Some(message),
+            location,
         }
     }
+
+    /// Pushes the frame of a caller, which is dropped if the call stack is
+    /// full.
+    pub fn push(&mut self, call: FuncCall) {
+        if self.len < N {
+            self.calls[self.len] = call;
+            self.len += 1;
+        } else {
+            self.truncated = true;
+        }
+    }
+
+    /// The recorded frames, innermost first.
+    pub fn calls(&self) -> &[FuncCall] {
+        &self.calls[..self.len]
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns `true` if frames have been dropped because the call stack was
+    /// full.
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// The rendered panic message, if the call stack has been started by a
+    /// panic with a message.
+    pub fn message(&self) -> Option<&PanicMessage> {
+        self.message.as_ref()
+    }
+
+    /// Where the panic fired, if known.
+    pub fn location(&self) -> Option<PanicLocation> {
+        self.location
+    }
+
+    /// Marks frames as dropped, for a call stack rebuilt from a truncated one.
+    #[cfg(all(feature = "std", target_os = "linux"))]
+    pub(crate) fn set_truncated(&mut self) {
+        self.truncated = true;
+    }
 }
 
-#[derive(Clone, Debug)]
+/// Writes one `#index: name at file:line:column` line per frame, and a marker line if the call
+/// stack is truncated.
+impl<const N: usize> Display for CallStack<N> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        for (i, call) in self.calls().iter().enumerate() {
+            writeln!(f, "#{}: {}", i, call)?;
+        }
+        if self.truncated {
+            writeln!(f, "... truncated after {} frames", N)?;
+        }
+        Ok(())
+    }
+}
+
+/// Where a panic fired
+///
+/// Unlike a [`Location`](https://doc.rust-lang.org/core/panic/struct.Location.html),
+/// it could also be built from the location of a real panic, which the panic
+/// hook only lends.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PanicLocation {
+    file: &'static str,
+    line: u32,
+    column: u32,
+}
+
+impl PanicLocation {
+    pub const fn new(file: &'static str, line: u32, column: u32) -> Self {
+        Self { file, line, column }
+    }
+
+    pub fn file(&self) -> &'static str {
+        self.file
+    }
+
+    pub fn line(&self) -> u32 {
+        self.line
+    }
+
+    pub fn column(&self) -> u32 {
+        self.column
+    }
+}
+
+impl From<&'static Location<'static>> for PanicLocation {
+    fn from(location: &'static Location<'static>) -> Self {
+        Self::new(location.file(), location.line(), location.column())
+    }
+}
+
+/// Writes `file:line:column`, like a `Location` does.
+impl Display for PanicLocation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{}:{}:{}", self.file, self.line, self.column)
+    }
+}
+
+/// A call made through `micro_call!(relay ...)`
+///
+/// The location is the one of the `micro_call!` invocation, i.e. the caller.
+#[derive(Copy, Clone, Debug)]
 pub struct FuncCall {
-    pub name: &'static str
+    /// The function path, e.g. `driver::init`, or the method name.
+    pub name: &'static str,
+    pub file: &'static str,
+    pub line: u32,
+    pub column: u32,
+    /// The type of the receiver, e.g. `Uart`, for method calls only.
+    pub receiver: Option<&'static str>,
+    /// Debug renderings of the arguments, recorded by
+    /// `micro_call!(relay_debug ...)` only.
+    pub args: Option<FuncArgs>,
 }
 
-pub struct Backtrace {
-    frames: Vec<BacktraceFrame>
+impl FuncCall {
+    const EMPTY: FuncCall = FuncCall {
+        name: "",
+        file: "",
+        line: 0,
+        column: 0,
+        receiver: None,
+        args: None,
+    };
 }
 
+/// Writes `Receiver::name(args) at file:line:column`, where `Receiver::` is
+/// omitted for function calls, and `(args)` if they have not been recorded.
+impl Display for FuncCall {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        if let Some(receiver) = self.receiver {
+            write!(f, "{}::", receiver)?;
+        }
+        f.write_str(self.name)?;
+        if let Some(args) = &self.args {
+            write!(f, "({})", args)?;
+        }
+        write!(f, " at {}:{}:{}", self.file, self.line, self.column)
+    }
+}
+
+#[doc(hidden)]
+pub fn infer_receiver_type<T: ?Sized>(_marker: &mut PhantomData<*const T>, _receiver: &T) {}
+
+/// Returns the name of the receiver type without references and module path,
+/// e.g. `Uart<dma::Dma>` for `&mut drivers::Uart<dma::Dma>`.
+#[doc(hidden)]
+pub fn receiver_type_name<T: ?Sized>(_marker: PhantomData<*const T>) -> &'static str {
+    let mut name = core::any::type_name::<T>();
+    loop {
+        if let Some(rest) = name.strip_prefix('&') {
+            name = rest;
+        } else if let Some(rest) = name.strip_prefix("mut ") {
+            name = rest;
+        } else {
+            break;
+        }
+    }
+    let path_end = name.find('<').unwrap_or(name.len());
+    match name[..path_end].rfind("::") {
+        Some(i) => &name[i + 2..],
+        None => name,
+    }
+}
+
+/// Comma-separated Debug renderings of the arguments of a call
+///
+/// The renderings are written into a fixed buffer of
+/// [`FUNC_ARGS_CAPACITY`](constant.FUNC_ARGS_CAPACITY.html) bytes, and anything
+/// beyond it is cut off at a character boundary.
+#[derive(Copy, Clone)]
+pub struct FuncArgs {
+    buf: [u8; FUNC_ARGS_CAPACITY],
+    len: usize,
+    truncated: bool,
+}
+
+impl Default for FuncArgs {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FuncArgs {
+    pub const fn new() -> Self {
+        Self {
+            buf: [0; FUNC_ARGS_CAPACITY],
+            len: 0,
+            truncated: false,
+        }
+    }
+
+    /// Renders `args` with `{:?}`, separated by `, `.
+    pub fn from_debug(args: &[&dyn Debug]) -> Self {
+        let mut func_args = Self::new();
+        for (i, arg) in args.iter().enumerate() {
+            let separator = if i == 0 { "" } else { ", " };
+            if write!(func_args, "{}{:?}", separator, arg).is_err() {
+                break;
+            }
+        }
+        func_args
+    }
+
+    pub fn as_str(&self) -> &str {
+        // Writes are always cut at character boundaries.
+        core::str::from_utf8(&self.buf[..self.len]).unwrap_or_default()
+    }
+
+    /// Returns `true` if the renderings did not fit in the buffer.
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    #[cfg(all(feature = "std", target_os = "linux"))]
+    pub(crate) fn set_truncated(&mut self) {
+        self.truncated = true;
+    }
+}
+
+/// Fails once the buffer is full, so that the rest of a rendering is skipped.
+impl Write for FuncArgs {
+    fn write_str(&mut self, s: &str) -> FmtResult {
+        if self.truncated || !write_truncated(&mut self.buf, &mut self.len, s) {
+            self.truncated = true;
+            return Err(core::fmt::Error);
+        }
+        Ok(())
+    }
+}
+
+/// Appends `s` to `buf[..*len]`, cut off at a character boundary if it does
+/// not fit, and returns whether it fit.
+pub(crate) fn write_truncated(buf: &mut [u8], len: &mut usize, s: &str) -> bool {
+    let mut end = s.len().min(buf.len() - *len);
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    buf[*len..*len + end].copy_from_slice(&s.as_bytes()[..end]);
+    *len += end;
+    end == s.len()
+}
+
+/// Writes the renderings, followed by `...` if they have been truncated.
+impl Display for FuncArgs {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_str(self.as_str())?;
+        if self.truncated {
+            f.write_str("...")?;
+        }
+        Ok(())
+    }
+}
+
+impl Debug for FuncArgs {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        Debug::fmt(self.as_str(), f)
+    }
+}
+
+/// Return addresses captured by walking the frame pointer chain
+///
+/// Capturing is only implemented on x86_64 and aarch64, and yields an empty
+/// backtrace on other architectures, where feature `frame_pointers` doesn't
+/// build. Addresses could be turned into function
+/// names and source locations on the host with the ELF of the program.
+#[derive(Copy, Clone, Debug)]
+pub struct Backtrace<const N: usize = DEFAULT_CALL_STACK_DEPTH> {
+    frames: [BacktraceFrame; N],
+    len: usize,
+    truncated: bool,
+}
+
+/// The largest distance between two frame pointers which is still believed to
+/// be a frame, rather than a register holding something else.
+const MAX_FRAME_SIZE: usize = 1 << 20;
+
+impl<const N: usize> Backtrace<N> {
+    /// Captures the frames above the caller of `capture`, innermost first,
+    /// skipping the innermost `skip` of them.
+    ///
+    /// The walk stops at a null or misaligned frame pointer, or one which is
+    /// not above the previous frame.
+    ///
+    /// # Safety
+    ///
+    /// Every function on the stack must keep a frame pointer, e.g. by building
+    /// with `-C force-frame-pointers=yes`, or the walk may read from arbitrary
+    /// addresses.
+    #[inline(never)]
+    pub unsafe fn capture(skip: usize) -> Self {
+        let mut backtrace = Self {
+            frames: [BacktraceFrame::EMPTY; N],
+            len: 0,
+            truncated: false,
+        };
+        let mut fp = frame_pointer();
+        let mut skip = skip;
+        while fp != 0 && fp.is_multiple_of(core::mem::align_of::<usize>()) {
+            // The saved frame pointer of the caller is followed by the return
+            // address into the caller.
+            let next_fp = *(fp as *const usize);
+            let ip = *(fp as *const usize).add(1);
+            if ip == 0 {
+                break;
+            }
+            if skip > 0 {
+                skip -= 1;
+            } else if backtrace.len < N {
+                backtrace.frames[backtrace.len] = BacktraceFrame { frame: Frame { ip, fp } };
+                backtrace.len += 1;
+            } else {
+                backtrace.truncated = true;
+                break;
+            }
+            if next_fp <= fp || next_fp - fp > MAX_FRAME_SIZE {
+                break;
+            }
+            fp = next_fp;
+        }
+        backtrace
+    }
+
+    /// The captured frames, innermost first.
+    pub fn frames(&self) -> &[BacktraceFrame] {
+        &self.frames[..self.len]
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns `true` if the walk was stopped because the backtrace was full.
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// Rebuilds a backtrace from its `(ip, fp)` frames, e.g. ones captured in
+    /// a forked child.
+    #[cfg(all(feature = "std", target_os = "linux"))]
+    pub(crate) fn from_frames<I: IntoIterator<Item = (usize, usize)>>(frames: I, truncated: bool) -> Self {
+        let mut backtrace = Self {
+            frames: [BacktraceFrame::EMPTY; N],
+            len: 0,
+            truncated,
+        };
+        for (ip, fp) in frames.into_iter().take(N) {
+            backtrace.frames[backtrace.len] = BacktraceFrame { frame: Frame { ip, fp } };
+            backtrace.len += 1;
+        }
+        backtrace
+    }
+}
+
+/// Writes one `#index: 0xaddress` line per frame, and a marker line if the
+/// backtrace is truncated.
+impl<const N: usize> Display for Backtrace<N> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        for (i, frame) in self.frames().iter().enumerate() {
+            writeln!(f, "#{}: {:#018x}", i, frame.ip())?;
+        }
+        if self.truncated {
+            writeln!(f, "... truncated after {} frames", N)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+#[inline(always)]
+fn frame_pointer() -> usize {
+    let fp: usize;
+    unsafe {
+        core::arch::asm!("mov {}, rbp", out(reg) fp, options(nomem, nostack, preserves_flags));
+    }
+    fp
+}
+
+// The frame record pointed to by x29 is laid out as on x86_64.
+#[cfg(target_arch = "aarch64")]
+#[inline(always)]
+fn frame_pointer() -> usize {
+    let fp: usize;
+    unsafe {
+        core::arch::asm!("mov {}, x29", out(reg) fp, options(nomem, nostack, preserves_flags));
+    }
+    fp
+}
+
+#[cfg(all(feature = "frame_pointers", not(any(target_arch = "x86_64", target_arch = "aarch64"))))]
+compile_error!("feature `frame_pointers` is only supported on x86_64 and aarch64");
+
+#[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+#[inline(always)]
+fn frame_pointer() -> usize {
+    0
+}
+
+#[derive(Copy, Clone, Debug)]
 pub struct BacktraceFrame {
     frame: Frame,
 }
 
+impl BacktraceFrame {
+    const EMPTY: BacktraceFrame = BacktraceFrame { frame: Frame { ip: 0, fp: 0 } };
+
+    /// The return address into the function of this frame.
+    pub fn ip(&self) -> usize {
+        self.frame.ip
+    }
+
+    /// The frame pointer of the function called by the one of this frame.
+    pub fn fp(&self) -> usize {
+        self.frame.fp
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
 pub struct Frame {
+    ip: usize,
+    fp: usize,
 }
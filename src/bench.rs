@@ -1,26 +1,50 @@
+use core::time::Duration;
+
 /// A trait describes how to obtain measure current time and obtain time
 /// duration.
 pub trait Time: Sync + Send {
 
     /// Starts the timer
-    fn start(&mut self);
+    fn start(&mut self) { }
 
     /// Ends the timer, and return the time duration between calling to
-    /// [`start`](trait.Time.html#tymethod.start) and
-    /// [`end`](trait.Time.html#tymethod.end).
-    fn end(&mut self);
+    /// [`start`](trait.Time.html#method.start) and
+    /// [`end`](trait.Time.html#method.end).
+    fn end(&mut self) { }
+
+    /// Returns the time elapsed since an arbitrary but fixed instant, e.g. the
+    /// boot of the device, so that durations are differences between two
+    /// calls.
+    ///
+    /// Reporters time tests with this method, as they only have a shared
+    /// reference to the timer. Timers not implementing it always read zero.
+    fn now(&self) -> Duration { Duration::ZERO }
 }
 
-struct VacuumTimer;
+/// A timer always reading zero, for targets without a clock
+pub struct VacuumTimer;
 
 impl Time for VacuumTimer {
     fn start(&mut self) { }
     fn end(&mut self) { }
 }
 
+/// A timer backed by `std::time::Instant`, measuring from its first use
+#[cfg(feature = "std")]
+pub struct StdTimer;
+
+#[cfg(feature = "std")]
+impl Time for StdTimer {
+    fn now(&self) -> Duration {
+        static EPOCH: std::sync::OnceLock<std::time::Instant> = std::sync::OnceLock::new();
+        EPOCH.get_or_init(std::time::Instant::now).elapsed()
+    }
+}
+
 #[cfg(feature = "spin_once")]
 static TIMER: spin::Once<&dyn Time> = spin::Once::new();
 
+#[cfg(feature = "spin_once")]
 pub fn set_timer(timer: &'static dyn Time) {
     if TIMER.is_completed() {
         panic!("timer has already been initialized");
@@ -28,3 +52,28 @@ pub fn set_timer(timer: &'static dyn Time) {
         TIMER.call_once(|| timer);
     }
 }
+
+/// Returns the timer, if one has been set.
+#[cfg(feature = "spin_once")]
+pub fn timer() -> Option<&'static dyn Time> {
+    TIMER.get().copied()
+}
+
+#[cfg(feature = "racy")]
+static mut TIMER_RACY: Option<&'static dyn Time> = None;
+
+/// A thread-unsafe version of [`set_timer`](fn.set_timer.html)
+///
+/// # Safety
+///
+/// This function is only safe to call when no test is running and no other
+/// thread is reading the timer.
+#[cfg(feature = "racy")]
+pub unsafe fn set_timer_racy(timer: &'static dyn Time) {
+    TIMER_RACY = Some(timer);
+}
+
+#[cfg(feature = "racy")]
+pub fn timer() -> Option<&'static dyn Time> {
+    unsafe { TIMER_RACY }
+}
@@ -0,0 +1,89 @@
+//! Rewrites the backtrace addresses of a micro_test log into
+//! `function (file:line)`
+//!
+//! ```text
+//! micro-test-symbolize <elf> [log] [--bias 0xoffset]
+//! ```
+//!
+//! The log is read from the standard input if not given, and written to the
+//! standard output with the addresses rewritten.
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::process;
+
+use micro_test::symbolize::Symbolizer;
+
+const USAGE: &str = "usage: micro-test-symbolize <elf> [log] [--bias 0xoffset]";
+
+fn parse_address(s: &str) -> Option<u64> {
+    match s.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+fn main() {
+    let mut elf_path = None;
+    let mut log_path = None;
+    let mut bias = 0;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--bias" => match args.next().as_deref().and_then(parse_address) {
+                Some(value) => bias = value,
+                None => {
+                    eprintln!("{}", USAGE);
+                    process::exit(2);
+                }
+            },
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            }
+            _ if elf_path.is_none() => elf_path = Some(arg),
+            _ if log_path.is_none() => log_path = Some(arg),
+            _ => {
+                eprintln!("{}", USAGE);
+                process::exit(2);
+            }
+        }
+    }
+    let elf_path = match elf_path {
+        Some(path) => path,
+        None => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    };
+
+    let elf = std::fs::read(&elf_path).unwrap_or_else(|e| {
+        eprintln!("micro-test-symbolize: cannot read {}: {}", elf_path, e);
+        process::exit(1);
+    });
+    let symbolizer = Symbolizer::with_bias(&elf, bias).unwrap_or_else(|e| {
+        eprintln!("micro-test-symbolize: {}: {}", elf_path, e);
+        process::exit(1);
+    });
+    let log: Box<dyn BufRead> = match &log_path {
+        Some(path) => match std::fs::File::open(path) {
+            Ok(file) => Box::new(BufReader::new(file)),
+            Err(e) => {
+                eprintln!("micro-test-symbolize: cannot read {}: {}", path, e);
+                process::exit(1);
+            }
+        },
+        None => Box::new(BufReader::new(io::stdin())),
+    };
+
+    let stdout = io::stdout();
+    let mut output = stdout.lock();
+    for line in log.lines() {
+        let line = line.unwrap_or_else(|e| {
+            eprintln!("micro-test-symbolize: cannot read log: {}", e);
+            process::exit(1);
+        });
+        if writeln!(output, "{}", symbolizer.symbolize_line(&line)).is_err() {
+            process::exit(1);
+        }
+    }
+}
@@ -1,70 +1,305 @@
 pub use micro_test_macros::micro_panic_relay;
 pub use micro_test_macros::micro_panic_receiver;
 
+use core::fmt::{Debug, Display, Formatter, Write};
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::backtrace::{self, Backtrace};
+
+/// The default capacity in bytes of [`PanicMessage`](struct.PanicMessage.html).
+pub const PANIC_MESSAGE_CAPACITY: usize = 128;
+
 pub struct PanicInfo<'a> {
     pub message: Option<&'a core::fmt::Arguments<'a>>,
-    pub location: &'a core::panic::Location<'a>
+    pub location: &'a core::panic::Location<'a>,
+    /// The failed assertion, if the panic comes from one of the
+    /// `micro_assert*!` or `micro_expect*!` macros.
+    pub assertion: Option<&'a AssertionFailure<'a>>,
+    /// The machine-level backtrace from where `micro_panic!` fires, captured
+    /// by [`handle_panic`](fn.handle_panic.html) with feature `frame_pointers`.
+    pub backtrace: Option<&'a Backtrace>,
 }
 
-#[cfg(feature = "spin_once")]
-static PANIC_HANDLER: spin::Once<fn(&PanicInfo)> = spin::Once::new();
+/// The kind of a failed assertion
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AssertionKind {
+    /// `micro_assert!` or `micro_expect!`
+    Bool,
+    /// `micro_assert_eq!` or `micro_expect_eq!`
+    Eq,
+    /// `micro_assert_ne!` or `micro_expect_ne!`
+    Ne,
+    /// Any other assertion, e.g. one built by user-defined macros
+    Custom,
+}
 
-pub fn set_panic_handler(handler: fn(&PanicInfo)) {
-    if PANIC_HANDLER.is_completed() {
-        panic!("micro_test panic handler has already been initialized");
-    } else {
-        PANIC_HANDLER.call_once(|| handler);
+/// A failed assertion in a structured form
+///
+/// Handlers could use this instead of the pre-formatted [`PanicInfo`]
+/// message, e.g. to emit machine-readable diffs.
+///
+/// [`PanicInfo`]: struct.PanicInfo.html
+#[derive(Copy, Clone, Debug)]
+pub struct AssertionFailure<'a> {
+    pub kind: AssertionKind,
+    /// The stringified asserted expression, or the left one of a comparison.
+    pub left_expr: &'a str,
+    /// The stringified right expression of a comparison.
+    pub right_expr: Option<&'a str>,
+    /// The value of the left expression of a comparison.
+    pub left: Option<&'a dyn Debug>,
+    /// The value of the right expression of a comparison.
+    pub right: Option<&'a dyn Debug>,
+    /// The message given by the user, if any.
+    pub message: Option<&'a core::fmt::Arguments<'a>>,
+}
+
+impl<'a> PanicInfo<'a> {
+    /// Checks whether the panic message contains `pattern`.
+    ///
+    /// The message is matched while being formatted, so this works without an
+    /// allocator. A panic without message only contains the empty pattern.
+    pub fn message_contains(&self, pattern: &str) -> bool {
+        let mut matcher = Matcher {
+            pattern: pattern.as_bytes(),
+            matched: 0,
+            found: pattern.is_empty(),
+        };
+        if let Some(message) = self.message {
+            let _ = write!(matcher, "{}", message);
+        }
+        matcher.found
+    }
+}
+
+/// A panic message rendered into a fixed buffer of `N` bytes
+///
+/// Unlike [`PanicInfo::message`], which only lives while the reporter handles
+/// the panic, the rendered message is relayed in the
+/// [`CallStack`](../backtrace/struct.CallStack.html) returned by the test.
+/// Anything beyond the capacity is cut off at a character boundary.
+///
+/// [`PanicInfo::message`]: struct.PanicInfo.html#structfield.message
+#[derive(Copy, Clone)]
+pub struct PanicMessage<const N: usize = PANIC_MESSAGE_CAPACITY> {
+    buf: [u8; N],
+    len: usize,
+    truncated: bool,
+}
+
+impl<const N: usize> Default for PanicMessage<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> PanicMessage<N> {
+    pub const fn new() -> Self {
+        Self {
+            buf: [0; N],
+            len: 0,
+            truncated: false,
+        }
+    }
+
+    /// Renders `message`.
+    pub fn from_args(message: &core::fmt::Arguments) -> Self {
+        let mut panic_message = Self::new();
+        let _ = panic_message.write_fmt(*message);
+        panic_message
+    }
+
+    pub fn as_str(&self) -> &str {
+        // Writes are always cut at character boundaries.
+        core::str::from_utf8(&self.buf[..self.len]).unwrap_or_default()
+    }
+
+    /// Returns `true` if the message did not fit in the buffer.
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    #[cfg(all(feature = "std", target_os = "linux"))]
+    pub(crate) fn set_truncated(&mut self) {
+        self.truncated = true;
+    }
+}
+
+/// Fails once the buffer is full, so that the rest of the message is skipped.
+impl<const N: usize> Write for PanicMessage<N> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        if self.truncated || !backtrace::write_truncated(&mut self.buf, &mut self.len, s) {
+            self.truncated = true;
+            return Err(core::fmt::Error);
+        }
+        Ok(())
+    }
+}
+
+/// Writes the message, followed by `...` if it has been truncated.
+impl<const N: usize> Display for PanicMessage<N> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.write_str(self.as_str())?;
+        if self.truncated {
+            f.write_str("...")?;
+        }
+        Ok(())
+    }
+}
+
+impl<const N: usize> Debug for PanicMessage<N> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        Debug::fmt(self.as_str(), f)
+    }
+}
+
+/// A streaming substring matcher fed by `core::fmt::Write`
+struct Matcher<'p> {
+    pattern: &'p [u8],
+    matched: usize,
+    found: bool,
+}
+
+impl<'p> Matcher<'p> {
+    /// Length of the longest prefix of `pattern` that is a suffix of the
+    /// matched prefix followed by `byte`.
+    fn step(&self, byte: u8) -> usize {
+        let pattern = self.pattern;
+        let mut len = self.matched + 1;
+        while len > 0 {
+            if pattern[len - 1] == byte
+                && pattern[..len - 1] == pattern[self.matched + 1 - len..self.matched]
+            {
+                return len;
+            }
+            len -= 1;
+        }
+        0
+    }
+}
+
+impl<'p> Write for Matcher<'p> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        for &byte in s.as_bytes() {
+            if self.found {
+                break;
+            }
+            self.matched = self.step(byte);
+            self.found = self.matched == self.pattern.len();
+        }
+        Ok(())
     }
 }
 
-pub fn handle_panic(panic_info: &PanicInfo) {
-    match PANIC_HANDLER.get() {
-        Some(panic_handler) => panic_handler(panic_info),
-        None => panic!("panic handler has not been initialized"),
+/// Passes a `micro_panic!` to the
+/// [`Report::assertion_failed`](../report/trait.Report.html#method.assertion_failed)
+/// method of the reporter
+///
+/// With feature `frame_pointers`, a backtrace from the caller is captured
+/// first, unless `panic_info` already has one.
+///
+/// Returns the rendered message, which `micro_panic!` relays in the call
+/// stack.
+#[inline(never)]
+pub fn handle_panic(panic_info: &PanicInfo) -> PanicMessage {
+    let reporter = crate::report::reporter();
+    #[cfg(feature = "frame_pointers")]
+    if panic_info.backtrace.is_none() {
+        // Safety: feature `frame_pointers` requires frame pointers to be
+        // kept everywhere.
+        let backtrace = unsafe { Backtrace::capture(1) };
+        reporter.assertion_failed(&PanicInfo {
+            backtrace: Some(&backtrace),
+            ..*panic_info
+        });
+    } else {
+        reporter.assertion_failed(panic_info);
     }
+    #[cfg(not(feature = "frame_pointers"))]
+    reporter.assertion_failed(panic_info);
+    match panic_info.message {
+        Some(message) => PanicMessage::from_args(message),
+        None => PanicMessage::new(),
+    }
+}
+
+static EXPECTATION_FAILURES: AtomicUsize = AtomicUsize::new(0);
+
+/// Reports a failed `micro_expect!` without returning from the test
+///
+/// The failure is passed to the reporter and counted, so that the test
+/// function fails when it finishes.
+pub fn handle_expectation_failure(panic_info: &PanicInfo) {
+    handle_panic(panic_info);
+    // Only load and store are used, which are available on targets without
+    // atomic read-modify-write instructions.
+    let failures = EXPECTATION_FAILURES.load(Ordering::Relaxed);
+    EXPECTATION_FAILURES.store(failures.wrapping_add(1), Ordering::Relaxed);
+}
+
+/// Returns the number of failed expectations so far
+///
+/// Test functions compare this before and after running their body to tell
+/// whether any expectation failed.
+pub fn expectation_failures() -> usize {
+    EXPECTATION_FAILURES.load(Ordering::Relaxed)
 }
 
 #[macro_export]
 macro_rules! micro_panic {
+    // Fails without reporting, for failures which have already been reported,
+    // e.g. by `micro_expect!`.
+    (@fail) => {
+        return ::core::result::Result::Err($crate::backtrace::CallStack::new())
+    };
+    // Fails with the call stack of a failure which has already been reported,
+    // without pushing a frame onto it.
+    (@fail $call_stack:expr) => {
+        return ::core::result::Result::Err($call_stack)
+    };
+    (@assertion $assertion:expr, $($arg:tt)*) => {
+        {
+            let location = ::core::panic::Location::caller();
+            let message = $crate::panic::handle_panic(&$crate::panic::PanicInfo {
+                message: Some(&format_args!($($arg)*)),
+                location,
+                assertion: Some($assertion),
+                backtrace: None,
+            });
+            return ::core::result::Result::Err($crate::backtrace::CallStack::from_panic(
+                message,
+                ::core::option::Option::Some($crate::backtrace::PanicLocation::from(location)),
+            ));
+        }
+    };
     ($arg:tt) => {
         {
-            $crate::panic::handle_panic(&$crate::panic::PanicInfo {
+            let location = ::core::panic::Location::caller();
+            let message = $crate::panic::handle_panic(&$crate::panic::PanicInfo {
                 message: Some(&format_args!($arg)),
-                location: ::core::panic::Location::caller(),
+                location,
+                assertion: None,
+                backtrace: None,
             });
-            return ::core::result::Result::Err($crate::backtrace::CallStack::new());
+            return ::core::result::Result::Err($crate::backtrace::CallStack::from_panic(
+                message,
+                ::core::option::Option::Some($crate::backtrace::PanicLocation::from(location)),
+            ));
         }
     };
     ($($arg:tt)*) => {
         {
-            $crate::panic::handle_panic(&$crate::panic::PanicInfo {
+            let location = ::core::panic::Location::caller();
+            let message = $crate::panic::handle_panic(&$crate::panic::PanicInfo {
                 message: Some(&format_args!($($arg)*)),
-                location: ::core::panic::Location::caller(),
+                location,
+                assertion: None,
+                backtrace: None,
             });
-            return ::core::result::Result::Err($crate::backtrace::CallStack::new());
+            return ::core::result::Result::Err($crate::backtrace::CallStack::from_panic(
+                message,
+                ::core::option::Option::Some($crate::backtrace::PanicLocation::from(location)),
+            ));
         }
     }
 }
-
-#[macro_export]
-macro_rules! micro_call {
-    (relay $target:ident($($arg:expr),* $(,)*)) => {
-        {
-            match $target($($arg),*) {
-                Ok(ret) => ret,
-                Err(mut call_stack) => {
-                    call_stack.calls.push($crate::backtrace::FuncCall {
-                        name: ::core::stringify!($target)
-                    });
-                    return ::core::result::Result::Err(call_stack);
-                }
-            }
-        }
-    };
-    (result $target:ident($($arg:expr),* $(,)*)) => {
-        $target($($arg),*)
-    };
-    (unwrap $target:ident($($arg:expr),* $(,)*)) => {
-        $target($($arg),*).unwrap()
-    };
-}
@@ -1,56 +1,298 @@
-use crate::test;
-use crate::backtrace;
+//! Reporting of test progress and failures
+//!
+//! Everything a test run reports goes through a single [`Report`]
+//! implementation, registered once with [`set_reporter`]: test functions
+//! report their metadata when they start, `micro_panic!` and the assertion
+//! macros report failures as they fire, and the runner reports the outcome of
+//! each test and of the whole run.
+//!
+//! To drive several outputs in one run, e.g. a console on UART and a
+//! machine-readable log, register a [`FanOut`] of them.
+//!
+//! Built-in reporters:
+//!
+//! - [`Libtest`] writes the pretty format of libtest.
+//! - [`Json`] writes the JSON event stream of libtest.
+//! - [`Tap`] writes TAP version 13.
+//! - [`JUnit`] writes a JUnit XML file, with feature `std`.
+//!
+//! [`Report`]: trait.Report.html
+//! [`set_reporter`]: fn.set_reporter.html
+//! [`FanOut`]: struct.FanOut.html
+//! [`Libtest`]: struct.Libtest.html
+//! [`Json`]: struct.Json.html
+//! [`Tap`]: struct.Tap.html
+//! [`JUnit`]: struct.JUnit.html
 
-pub struct Reporter {
-    pub metadata: Option<fn(test::Metadata)>,
-    pub result: Option<fn(test::Result)>,
-    pub call_stack: Option<fn(backtrace::CallStack)>,
+use core::fmt::{Display, Formatter, Result as FmtResult, Write};
+use core::ops::{Deref, DerefMut};
+
+use crate::backtrace::CallStack;
+use crate::panic::PanicInfo;
+use crate::runner::Summary;
+use crate::sync::{Mutex, MutexGuard};
+use crate::test::Metadata;
+
+#[cfg(feature = "std")]
+mod junit;
+mod json;
+mod libtest;
+mod tap;
+
+pub use self::json::{Json, JSON_FAILURE_OUTPUT};
+pub use self::libtest::{Libtest, LIBTEST_FAILURES, LIBTEST_FAILURE_OUTPUT};
+pub use self::tap::Tap;
+#[cfg(feature = "std")]
+pub use self::junit::JUnit;
+
+/// The outcome of a finished test
+#[derive(Copy, Clone, Debug)]
+pub enum TestOutcome<'a> {
+    Passed,
+    Failed {
+        /// The call stack returned by the test, if it returned one.
+        call_stack: Option<&'a CallStack>,
+        /// Why the test failed, when no panic tells it, e.g. a
+        /// `#[micro_should_fail]` test which did not fail.
+        note: Option<&'a str>,
+    },
+    Ignored,
 }
 
-#[cfg(feature = "spin_once")]
-static REPORTER: spin::Once<&Reporter> = spin::Once::new();
+/// A receiver of test events
+///
+/// Every method does nothing by default, so that implementations only handle
+/// the events they are interested in. Methods take `&self`, as the reporter is
+/// shared by every test; implementations keeping state, such as counters or
+/// an output sink, should use interior mutability.
+///
+/// # Examples
+///
+/// ```rust
+/// use core::sync::atomic::{AtomicUsize, Ordering};
+/// use micro_test::panic::PanicInfo;
+/// use micro_test::report::{set_reporter, Report};
+/// use micro_test::test::Metadata;
+///
+/// struct Console {
+///     failures: AtomicUsize,
+/// }
+///
+/// impl Report for Console {
+///     fn test_started(&self, metadata: &Metadata) {
+///         println!("testing {} ...", metadata);
+///     }
+///
+///     fn assertion_failed(&self, info: &PanicInfo) {
+///         self.failures.fetch_add(1, Ordering::Relaxed);
+///         println!("FAILED: {}", info);
+///     }
+/// }
+///
+/// static CONSOLE: Console = Console { failures: AtomicUsize::new(0) };
+///
+/// fn main() {
+///     set_reporter(&CONSOLE);
+/// }
+/// ```
+pub trait Report: Sync {
+    /// A run of `test_count` tests starts.
+    fn run_started(&self, _test_count: usize) {}
 
-#[cfg(feature = "spin_once")]
-pub fn report_metadata(metadata: test::Metadata) {
-    const NOT_INITIALIZED_ERROR: &'static str = "metadata reporter has not been initialized";
-    match REPORTER.get() {
-        Some(report) => match report.metadata {
-            Some(f) => f(metadata),
-            None => panic!("{}", NOT_INITIALIZED_ERROR),
+    /// A test selected by the filter starts. Ignored tests are started too,
+    /// with `metadata.ignore` set, but their body does not run.
+    fn test_started(&self, _metadata: &Metadata) {}
+
+    /// A `micro_panic!`, failed assertion or failed expectation fires in the
+    /// running test.
+    fn assertion_failed(&self, _info: &PanicInfo) {}
+
+    /// A started test has finished.
+    fn test_finished(&self, _metadata: &Metadata, _outcome: &TestOutcome) {}
+
+    /// The run has finished.
+    fn run_finished(&self, _summary: &Summary) {}
+}
+
+/// A reporter forwarding every event to a fixed list of reporters, in order
+///
+/// # Examples
+///
+/// ```rust
+/// use micro_test::report::{set_reporter, FanOut, Report};
+///
+/// struct Console;
+/// impl Report for Console {}
+///
+/// struct Log;
+/// impl Report for Log {}
+///
+/// static REPORTER: FanOut = FanOut::new(&[&Console, &Log]);
+///
+/// fn main() {
+///     set_reporter(&REPORTER);
+/// }
+/// ```
+#[derive(Copy, Clone)]
+pub struct FanOut<'a> {
+    reporters: &'a [&'a dyn Report],
+}
+
+impl<'a> FanOut<'a> {
+    pub const fn new(reporters: &'a [&'a dyn Report]) -> Self {
+        Self { reporters }
+    }
+
+    pub fn reporters(&self) -> &'a [&'a dyn Report] {
+        self.reporters
+    }
+}
+
+impl Report for FanOut<'_> {
+    fn run_started(&self, test_count: usize) {
+        for reporter in self.reporters {
+            reporter.run_started(test_count);
+        }
+    }
+
+    fn test_started(&self, metadata: &Metadata) {
+        for reporter in self.reporters {
+            reporter.test_started(metadata);
+        }
+    }
+
+    fn assertion_failed(&self, info: &PanicInfo) {
+        for reporter in self.reporters {
+            reporter.assertion_failed(info);
+        }
+    }
+
+    fn test_finished(&self, metadata: &Metadata, outcome: &TestOutcome) {
+        for reporter in self.reporters {
+            reporter.test_finished(metadata, outcome);
+        }
+    }
+
+    fn run_finished(&self, summary: &Summary) {
+        for reporter in self.reporters {
+            reporter.run_finished(summary);
         }
-        None => panic!("{}", NOT_INITIALIZED_ERROR),
     }
 }
 
-#[cfg(feature = "spin_once")]
-pub fn report_result(result: test::Result) {
-    const NOT_INITIALIZED_ERROR: &'static str = "result reporter has not been initialized";
-    match REPORTER.get() {
-        Some(report) => match report.result {
-            Some(f) => f(result),
-            None => panic!("{}", NOT_INITIALIZED_ERROR),
-        },
-        None => panic!("{}", NOT_INITIALIZED_ERROR),
+/// Writes the path of a test function without the crate name, as libtest
+/// does, e.g. `tests::test_add`.
+struct TestName<'a>(&'a Metadata);
+
+impl Display for TestName<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self.0.module_path.split_once("::") {
+            Some((_, path)) => write!(f, "{}::{}", path, self.0.name),
+            None => f.write_str(self.0.name),
+        }
     }
 }
 
-#[cfg(feature = "spin_once")]
-pub fn report_call_stack(call_stack: backtrace::CallStack) {
-    const NOT_INITIALIZED_ERROR: &'static str = "call stack reporter has not been initialized";
-    match REPORTER.get() {
-        Some(report) => match report.call_stack {
-            Some(f) => f(call_stack),
-            None => panic!("{}", NOT_INITIALIZED_ERROR),
-        },
-        None => panic!("{}", NOT_INITIALIZED_ERROR),
+/// The sink of a built-in reporter, locked together with the state of the
+/// reporter
+struct Locked<W, S> {
+    inner: Mutex<WithSink<W, S>>,
+}
+
+/// The state of a built-in reporter and its sink, which the state derefs to
+/// and `write!` writes into
+struct WithSink<W, S> {
+    sink: W,
+    state: S,
+}
+
+impl<W, S> Locked<W, S> {
+    const fn new(sink: W, state: S) -> Self {
+        Self {
+            inner: Mutex::new(WithSink { sink, state }),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, WithSink<W, S>> {
+        self.inner.lock()
+    }
+
+    /// Locks unless the lock is held, e.g. by a reporter method interrupted
+    /// by a panic from the sink, whose report is then dropped rather than
+    /// deadlocking.
+    fn try_lock(&self) -> Option<MutexGuard<'_, WithSink<W, S>>> {
+        self.inner.try_lock()
+    }
+
+    fn with_sink<R, F: FnOnce(&mut W) -> R>(&self, f: F) -> R {
+        f(&mut self.lock().sink)
+    }
+}
+
+impl<W: Write, S> WithSink<W, S> {
+    fn write_fmt(&mut self, args: core::fmt::Arguments) {
+        // Errors from the sink can't be reported anywhere, so they are dropped.
+        let _ = self.sink.write_fmt(args);
+    }
+}
+
+impl<W, S> Deref for WithSink<W, S> {
+    type Target = S;
+
+    fn deref(&self) -> &S {
+        &self.state
+    }
+}
+
+impl<W, S> DerefMut for WithSink<W, S> {
+    fn deref_mut(&mut self) -> &mut S {
+        &mut self.state
+    }
+}
+
+/// Writes a value with some characters escaped, as the format of a reporter
+/// requires
+///
+/// `escape` returns the escape sequence of a character, or `None` if the
+/// character is written as is.
+struct Escaped<T> {
+    value: T,
+    escape: fn(char) -> Option<&'static str>,
+}
+
+impl<T: Display> Display for Escaped<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let mut escaper = Escaper { f, escape: self.escape };
+        write!(escaper, "{}", self.value)
+    }
+}
+
+struct Escaper<'a, 'f> {
+    f: &'a mut Formatter<'f>,
+    escape: fn(char) -> Option<&'static str>,
+}
+
+impl Write for Escaper<'_, '_> {
+    fn write_str(&mut self, s: &str) -> FmtResult {
+        let mut start = 0;
+        for (i, c) in s.char_indices() {
+            if let Some(escaped) = (self.escape)(c) {
+                self.f.write_str(&s[start..i])?;
+                self.f.write_str(escaped)?;
+                start = i + c.len_utf8();
+            }
+        }
+        self.f.write_str(&s[start..])
     }
 }
 
 #[cfg(feature = "spin_once")]
-/// Sets the global test metadata and result processor
+static REPORTER: spin::Once<&'static dyn Report> = spin::Once::new();
+
+/// Sets the global reporter
 ///
-/// This function may only be called once in the lifetime of a program. Any test
-/// function executed prior to `set_reporter` will panic.
+/// This function may only be called once in the lifetime of a program. Any
+/// test function executed prior to `set_reporter` will panic.
 ///
 /// # Panics
 ///
@@ -62,43 +304,20 @@ pub fn report_call_stack(call_stack: backtrace::CallStack) {
 /// feature `racy` should be turned on and [`set_reporter_racy`] will be
 /// available then.
 ///
-/// # Examples
-///
-/// ```rust
-/// use micro_test::test::Metadata;
-/// use micro_test::report::{Reporter, set_reporter};
-/// use micro_test::test::Result as TestResult;
-/// fn print_metadata(_metadata: Metadata) { }
-/// fn print_result(_result: TestResult) { }
-/// fn main() {
-///     set_reporter(&Reporter {
-///         metadata: Some(print_metadata),
-///         result: Some(print_result),
-///         call_stack: None,
-///     });
-/// }
-/// ```
-///
 /// If `set_reporter` is called multiple times, it will panic.
 /// ```rust,should_panic
-/// # use micro_test::test::Metadata;
-/// # use micro_test::report::{Reporter, set_reporter};
-/// # use micro_test::test::Result as TestResult;
-/// # fn print_metadata(_metadata: Metadata) { }
-/// # fn print_result(_result: TestResult) { }
-/// # fn main() {
-/// static REPORTER: &Reporter = &Reporter {
-///     metadata: Some(print_metadata),
-///     result: Some(print_result),
-///     call_stack: None,
-/// };
-/// set_reporter(&REPORTER);
-/// set_reporter(&REPORTER);
-/// # }
+/// # use micro_test::report::{set_reporter, Report};
+/// struct Quiet;
+///
+/// impl Report for Quiet {}
+///
+/// set_reporter(&Quiet);
+/// set_reporter(&Quiet);
 /// ```
 ///
 /// [`set_reporter_racy`]: fn.set_reporter_racy.html
-pub fn set_reporter(reporter: &'static Reporter) {
+#[cfg(feature = "spin_once")]
+pub fn set_reporter(reporter: &'static dyn Report) {
     if REPORTER.is_completed() {
         panic!("reporter has already been initialized");
     } else {
@@ -106,64 +325,39 @@ pub fn set_reporter(reporter: &'static Reporter) {
     }
 }
 
-#[cfg(feature = "racy")]
-static mut REPORTER: &Reporter = &Reporter {
-    metadata: None,
-    result: None,
-    call_stack: None,
-};
+/// Returns the global reporter
+///
+/// # Panics
+///
+/// This function will panic if no reporter has been set.
+#[cfg(feature = "spin_once")]
+pub fn reporter() -> &'static dyn Report {
+    match REPORTER.get() {
+        Some(reporter) => *reporter,
+        None => panic!("reporter has not been initialized"),
+    }
+}
 
 #[cfg(feature = "racy")]
+static mut REPORTER_RACY: Option<&'static dyn Report> = None;
+
 /// A thread-unsafe version of [`set_reporter`]
 ///
-/// This function is available unless you disable the default feature of this
-/// crate, which includes feature `spin_once` feature only for now.
-///
 /// # Safety
 ///
-/// This function is only safe to call when no other processor setting functions
-/// are stilling initializing the processor.
+/// This function is only safe to call when no test is running and no other
+/// thread is reading the reporter.
 ///
 /// [`set_reporter`]: fn.set_reporter.html
-pub unsafe fn set_reporter_racy(reporter: &'static Reporter) {
-    REPORTER_RACY = reporter;
-}
-
 #[cfg(feature = "racy")]
-#[doc(hidden)]
-pub fn report_metadata(metadata: test::Metadata) {
-    const NOT_INITIALIZED_ERROR: &'static str = "metadata reporter has not been initialized";
-    unsafe {
-        if let Some(f) = REPORTER_RACY.metadata {
-            f(metadata);
-        } else {
-            panic!("{}", NOT_INITIALIZED_ERROR);
-        }
-    }
-}
-
-#[cfg(feature = "racy")]
-#[doc(hidden)]
-pub fn report_result(result: test::Result) {
-    const NOT_INITIALIZED_ERROR: &'static str = "result reporter has not been initialized";
-    unsafe {
-        if let Some(f) = REPORTER_RACY.result {
-            f(result);
-        } else {
-            panic!("{}", NOT_INITIALIZED_ERROR);
-        }
-    }
+pub unsafe fn set_reporter_racy(reporter: &'static dyn Report) {
+    REPORTER_RACY = Some(reporter);
 }
 
 #[cfg(feature = "racy")]
-#[doc(hidden)]
-pub fn report_call_stack(call_stack: backtrace::CallStack) {
-    const NOT_INITIALIZED_ERROR: &'static str = "call stack reporter has not been initialized";
-    unsafe {
-        if let Some(f) = REPORTER_RACY.call_stack {
-            f(call_stack);
-        } else {
-            panic!("{}", NOT_INITIALIZED_ERROR);
-        }
+pub fn reporter() -> &'static dyn Report {
+    match unsafe { REPORTER_RACY } {
+        Some(reporter) => reporter,
+        None => panic!("reporter has not been initialized"),
     }
 }
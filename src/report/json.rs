@@ -0,0 +1,164 @@
+use core::fmt::Write;
+use core::time::Duration;
+
+use crate::bench;
+use crate::panic::{PanicInfo, PanicMessage};
+use crate::runner::Summary;
+use crate::test::Metadata;
+
+use super::{Escaped, Locked, Report, TestName, TestOutcome};
+
+/// The default capacity in bytes of the `stdout` of a failed test written by
+/// [`Json`](struct.Json.html).
+pub const JSON_FAILURE_OUTPUT: usize = 512;
+
+/// A reporter writing the newline-delimited JSON events of libtest into a
+/// [`core::fmt::Write`] sink
+///
+/// The events are the ones of `cargo test -- -Z unstable-options --format
+/// json`, so that tools consuming them, e.g. `cargo2junit` or IDE test
+/// explorers, could consume micro_test runs as well:
+///
+/// ```text
+/// { "type": "suite", "event": "started", "test_count": 2 }
+/// { "type": "test", "event": "started", "name": "tests::test_add" }
+/// { "type": "test", "name": "tests::test_add", "event": "ok" }
+/// { "type": "test", "event": "started", "name": "tests::test_sub" }
+/// { "type": "test", "name": "tests::test_sub", "event": "failed", "stdout": "assertion failed: `1 - 1 == 1`: src/lib.rs:12:9\n" }
+/// { "type": "suite", "event": "failed", "passed": 1, "failed": 1, "ignored": 0, "measured": 0, "filtered_out": 0, "exec_time": 0.000 }
+/// ```
+///
+/// Tests are named as by [`Libtest`](struct.Libtest.html). The `stdout` of a
+/// failed test holds its panic messages and relayed call stack, kept in a
+/// buffer of `OUTPUT` bytes until the test finishes.
+pub struct Json<W, const OUTPUT: usize = JSON_FAILURE_OUTPUT> {
+    inner: Locked<W, Inner<OUTPUT>>,
+}
+
+struct Inner<const OUTPUT: usize> {
+    /// The output of the running test.
+    output: PanicMessage<OUTPUT>,
+    /// When the run started, if a timer has been set.
+    started_at: Option<Duration>,
+}
+
+impl<W, const OUTPUT: usize> Json<W, OUTPUT> {
+    pub const fn new(sink: W) -> Self {
+        Self {
+            inner: Locked::new(
+                sink,
+                Inner {
+                    output: PanicMessage::new(),
+                    started_at: None,
+                },
+            ),
+        }
+    }
+
+    /// Calls `f` with the sink, e.g. to flush it or to read what has been
+    /// written into it.
+    pub fn with_sink<R, F: FnOnce(&mut W) -> R>(&self, f: F) -> R {
+        self.inner.with_sink(f)
+    }
+}
+
+impl<W: Write + Send, const OUTPUT: usize> Report for Json<W, OUTPUT> {
+    fn run_started(&self, test_count: usize) {
+        let mut inner = self.inner.lock();
+        inner.started_at = bench::timer().map(|timer| timer.now());
+        writeln!(inner, r#"{{ "type": "suite", "event": "started", "test_count": {} }}"#, test_count);
+    }
+
+    fn test_started(&self, metadata: &Metadata) {
+        let mut inner = self.inner.lock();
+        inner.output = PanicMessage::new();
+        writeln!(
+            inner,
+            r#"{{ "type": "test", "event": "started", "name": "{}" }}"#,
+            escaped(TestName(metadata)),
+        );
+    }
+
+    fn assertion_failed(&self, info: &PanicInfo) {
+        let mut inner = match self.inner.try_lock() {
+            Some(inner) => inner,
+            None => return,
+        };
+        let _ = writeln!(inner.output, "{}", info);
+    }
+
+    fn test_finished(&self, metadata: &Metadata, outcome: &TestOutcome) {
+        let mut inner = self.inner.lock();
+        let name = escaped(TestName(metadata));
+        match *outcome {
+            TestOutcome::Passed => {
+                writeln!(inner, r#"{{ "type": "test", "name": "{}", "event": "ok" }}"#, name);
+            }
+            TestOutcome::Failed { call_stack, note } => {
+                let mut output = inner.output;
+                if let Some(note) = note {
+                    let _ = writeln!(output, "note: {}", note);
+                }
+                if let Some(call_stack) = call_stack {
+                    let _ = write!(output, "{}", call_stack);
+                }
+                writeln!(
+                    inner,
+                    r#"{{ "type": "test", "name": "{}", "event": "failed", "stdout": "{}" }}"#,
+                    name,
+                    escaped(output),
+                );
+            }
+            TestOutcome::Ignored => match metadata.ignore_message {
+                Some(message) => writeln!(
+                    inner,
+                    r#"{{ "type": "test", "name": "{}", "event": "ignored", "message": "{}" }}"#,
+                    name,
+                    escaped(message),
+                ),
+                None => writeln!(inner, r#"{{ "type": "test", "name": "{}", "event": "ignored" }}"#, name),
+            },
+        }
+    }
+
+    fn run_finished(&self, summary: &Summary) {
+        let mut inner = self.inner.lock();
+        let elapsed = match (inner.started_at, bench::timer()) {
+            (Some(started_at), Some(timer)) => timer.now().saturating_sub(started_at),
+            _ => Duration::ZERO,
+        };
+        writeln!(
+            inner,
+            concat!(
+                r#"{{ "type": "suite", "event": "{}", "passed": {}, "failed": {}, "ignored": {}, "#,
+                r#""measured": 0, "filtered_out": {}, "exec_time": {:.3} }}"#,
+            ),
+            if summary.is_ok() { "ok" } else { "failed" },
+            summary.passed,
+            summary.failed,
+            summary.ignored,
+            summary.filtered_out,
+            elapsed.as_secs_f64(),
+        );
+    }
+}
+
+/// Writes a value escaped for a JSON string.
+fn escaped<T>(value: T) -> Escaped<T> {
+    Escaped { value, escape: escape_json }
+}
+
+fn escape_json(c: char) -> Option<&'static str> {
+    const CONTROL: [&str; 0x20] = [
+        "\\u0000", "\\u0001", "\\u0002", "\\u0003", "\\u0004", "\\u0005", "\\u0006", "\\u0007",
+        "\\u0008", "\\t", "\\n", "\\u000b", "\\u000c", "\\r", "\\u000e", "\\u000f",
+        "\\u0010", "\\u0011", "\\u0012", "\\u0013", "\\u0014", "\\u0015", "\\u0016", "\\u0017",
+        "\\u0018", "\\u0019", "\\u001a", "\\u001b", "\\u001c", "\\u001d", "\\u001e", "\\u001f",
+    ];
+    match c {
+        '"' => Some("\\\""),
+        '\\' => Some("\\\\"),
+        c if (c as u32) < 0x20 => Some(CONTROL[c as usize]),
+        _ => None,
+    }
+}
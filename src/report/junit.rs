@@ -0,0 +1,244 @@
+use std::fmt::Write;
+use std::time::Duration;
+
+use crate::bench;
+use crate::panic::PanicInfo;
+use crate::runner::Summary;
+use crate::sync::Mutex;
+use crate::test::Metadata;
+
+use super::{Escaped, Report, TestOutcome};
+
+/// A reporter writing a JUnit XML file, as read by Jenkins or GitLab
+///
+/// Each test becomes a `<testcase>` whose `classname` is its target and whose
+/// `name` is its feature, or the name of the test function if it has none. A
+/// failed test holds a `<failure>` with its first panic message as `message`,
+/// and every panic message and the relayed call stack as text; an ignored
+/// test holds a `<skipped>`.
+///
+/// If a [`Time`](../bench/trait.Time.html) has been set with
+/// [`bench::set_timer`](../bench/fn.set_timer.html), test cases have a
+/// `time` in seconds.
+///
+/// As the `<testsuite>` element starts with the counts of the run, test cases
+/// are kept in memory and the file is written when the run finishes. An error
+/// writing it is kept for [`take_error`](#method.take_error).
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use micro_test::backtrace::CallStack;
+/// use micro_test::report::JUnit;
+/// use micro_test::runner::{self, Filter};
+///
+/// static JUNIT: JUnit = JUnit::new("target/micro-test.xml");
+///
+/// fn test_runner(tests: &[&dyn Fn() -> Result<(), CallStack>]) {
+///     micro_test::bench::set_timer(&micro_test::bench::StdTimer);
+///     let summary = runner::run_with_reporter(tests, Filter::Substring(""), &JUNIT);
+///     if let Some(error) = JUNIT.take_error() {
+///         panic!("cannot write {}: {}", JUNIT.path(), error);
+///     }
+///     assert!(summary.is_ok());
+/// }
+/// # fn main() { test_runner(&[]); }
+/// ```
+pub struct JUnit {
+    path: &'static str,
+    inner: Mutex<Inner>,
+}
+
+struct Inner {
+    /// The `<testcase>` elements written so far.
+    test_cases: String,
+    /// The name of the crate of the tests, used as the name of the suite.
+    suite: &'static str,
+    /// When the running test started, if a timer has been set.
+    started_at: Option<Duration>,
+    /// When the run started, if a timer has been set.
+    run_started_at: Option<Duration>,
+    /// The first panic message of the running test.
+    message: String,
+    /// Every panic of the running test.
+    output: String,
+    /// The error writing the file, if any.
+    error: Option<std::io::Error>,
+}
+
+impl JUnit {
+    /// Creates a reporter writing to the file at `path` once the run finishes.
+    pub const fn new(path: &'static str) -> Self {
+        Self {
+            path,
+            inner: Mutex::new(Inner {
+                test_cases: String::new(),
+                suite: "micro_test",
+                started_at: None,
+                run_started_at: None,
+                message: String::new(),
+                output: String::new(),
+                error: None,
+            }),
+        }
+    }
+
+    pub fn path(&self) -> &'static str {
+        self.path
+    }
+
+    /// Takes the error writing the file when the last run finished, if any.
+    pub fn take_error(&self) -> Option<std::io::Error> {
+        self.inner.lock().error.take()
+    }
+}
+
+fn now() -> Option<Duration> {
+    bench::timer().map(|timer| timer.now())
+}
+
+impl Report for JUnit {
+    fn run_started(&self, _test_count: usize) {
+        let mut inner = self.inner.lock();
+        inner.test_cases.clear();
+        inner.run_started_at = now();
+    }
+
+    fn test_started(&self, metadata: &Metadata) {
+        let mut inner = self.inner.lock();
+        if let Some(path) = metadata.module_path.split("::").next() {
+            inner.suite = path;
+        }
+        inner.message.clear();
+        inner.output.clear();
+        inner.started_at = now();
+    }
+
+    fn assertion_failed(&self, info: &PanicInfo) {
+        let mut inner = match self.inner.try_lock() {
+            Some(inner) => inner,
+            None => return,
+        };
+        if inner.message.is_empty() {
+            if let Some(message) = info.message {
+                let _ = write!(inner.message, "{}", message);
+            }
+        }
+        let _ = writeln!(inner.output, "{}", info);
+    }
+
+    fn test_finished(&self, metadata: &Metadata, outcome: &TestOutcome) {
+        let mut inner = self.inner.lock();
+        let inner = &mut *inner;
+        let _ = write!(
+            inner.test_cases,
+            "    <testcase classname=\"{}\" name=\"{}\"",
+            escaped_attribute(metadata.target),
+            escaped_attribute(metadata.feature.unwrap_or(metadata.name)),
+        );
+        let elapsed = match (inner.started_at, now()) {
+            (Some(started_at), Some(now)) if !metadata.ignore => Some(now.saturating_sub(started_at)),
+            _ => None,
+        };
+        if let Some(elapsed) = elapsed {
+            let _ = write!(inner.test_cases, " time=\"{:.3}\"", elapsed.as_secs_f64());
+        }
+        match *outcome {
+            TestOutcome::Passed => inner.test_cases.push_str("/>\n"),
+            TestOutcome::Ignored => {
+                inner.test_cases.push_str(">\n");
+                match metadata.ignore_message {
+                    Some(message) => {
+                        let _ = writeln!(inner.test_cases, "      <skipped message=\"{}\"/>", escaped_attribute(message));
+                    }
+                    None => inner.test_cases.push_str("      <skipped/>\n"),
+                }
+                inner.test_cases.push_str("    </testcase>\n");
+            }
+            TestOutcome::Failed { call_stack, note } => {
+                if let Some(note) = note {
+                    if inner.message.is_empty() {
+                        inner.message.push_str(note);
+                    }
+                    let _ = writeln!(inner.output, "note: {}", note);
+                }
+                if let Some(call_stack) = call_stack {
+                    let _ = write!(inner.output, "{}", call_stack);
+                }
+                inner.test_cases.push_str(">\n");
+                let _ = writeln!(
+                    inner.test_cases,
+                    "      <failure message=\"{}\">{}</failure>",
+                    escaped_attribute(&inner.message),
+                    escaped(&inner.output),
+                );
+                inner.test_cases.push_str("    </testcase>\n");
+            }
+        }
+    }
+
+    fn run_finished(&self, summary: &Summary) {
+        let mut inner = self.inner.lock();
+        let mut xml = String::new();
+        xml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        xml.push_str("<testsuites>\n");
+        let _ = write!(
+            xml,
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"0\" skipped=\"{}\"",
+            escaped_attribute(inner.suite),
+            summary.passed + summary.failed + summary.ignored,
+            summary.failed,
+            summary.ignored,
+        );
+        if let (Some(started_at), Some(now)) = (inner.run_started_at, now()) {
+            let _ = write!(xml, " time=\"{:.3}\"", now.saturating_sub(started_at).as_secs_f64());
+        }
+        xml.push_str(">\n");
+        xml.push_str(&inner.test_cases);
+        xml.push_str("  </testsuite>\n");
+        xml.push_str("</testsuites>\n");
+        inner.error = std::fs::write(self.path, xml).err();
+    }
+}
+
+/// Writes a value escaped for XML text.
+///
+/// Control characters other than tabs and line feeds can't appear in XML 1.0,
+/// even as character references, so they are replaced with U+FFFD.
+fn escaped<T>(value: T) -> Escaped<T> {
+    Escaped {
+        value,
+        escape: |c| match c {
+            '\t' | '\n' => None,
+            '\r' => Some("&#13;"),
+            _ => escape_markup(c),
+        },
+    }
+}
+
+/// Writes a value escaped for XML attribute values, where tabs and line
+/// breaks are written as character references, as parsers normalize them
+/// into spaces otherwise.
+fn escaped_attribute<T>(value: T) -> Escaped<T> {
+    Escaped {
+        value,
+        escape: |c| match c {
+            '\t' => Some("&#9;"),
+            '\n' => Some("&#10;"),
+            '\r' => Some("&#13;"),
+            _ => escape_markup(c),
+        },
+    }
+}
+
+fn escape_markup(c: char) -> Option<&'static str> {
+    match c {
+        '&' => Some("&amp;"),
+        '<' => Some("&lt;"),
+        '>' => Some("&gt;"),
+        '"' => Some("&quot;"),
+        '\'' => Some("&apos;"),
+        '\u{0}'..='\u{1f}' => Some("\u{fffd}"),
+        _ => None,
+    }
+}
@@ -0,0 +1,201 @@
+use core::fmt::Write;
+
+use crate::panic::{PanicInfo, PanicMessage};
+use crate::runner::Summary;
+use crate::test::Metadata;
+
+use super::{Locked, Report, TestName, TestOutcome};
+
+/// The default number of failures kept by [`Libtest`](struct.Libtest.html)
+/// until the end of the run.
+pub const LIBTEST_FAILURES: usize = 8;
+
+/// The default capacity in bytes of the output kept for each failure by
+/// [`Libtest`](struct.Libtest.html).
+pub const LIBTEST_FAILURE_OUTPUT: usize = 512;
+
+/// A reporter writing the pretty format of libtest into a
+/// [`core::fmt::Write`] sink, e.g. a UART wrapper
+///
+/// The output is the one of `cargo test -- --test-threads=1`, so that tools
+/// parsing libtest output could parse it as well:
+///
+/// ```text
+///
+/// running 2 tests
+/// test tests::test_add ... ok
+/// test tests::test_sub ... FAILED
+///
+/// failures:
+///
+/// ---- tests::test_sub stdout ----
+/// assertion failed: `1 - 1 == 1`: src/lib.rs:12:9
+///
+///
+/// failures:
+///     tests::test_sub
+///
+/// test result: FAILED. 1 passed; 1 failed; 0 ignored; 0 measured; 0 filtered out; finished in 0.00s
+///
+/// ```
+///
+/// Tests are named after their function and module path, without the crate
+/// name. Test runs are not timed, hence always finish in `0.00s`.
+///
+/// The failures section is written at the end of the run, so the panic
+/// messages and relayed call stack of up to `FAILURES` failed tests are kept
+/// until then, each in a buffer of `OUTPUT` bytes. Output beyond the capacity
+/// is cut off, and failures beyond it are counted in a final `... and N more`
+/// line.
+pub struct Libtest<
+    W,
+    const FAILURES: usize = LIBTEST_FAILURES,
+    const OUTPUT: usize = LIBTEST_FAILURE_OUTPUT,
+> {
+    inner: Locked<W, Inner<FAILURES, OUTPUT>>,
+}
+
+struct Inner<const FAILURES: usize, const OUTPUT: usize> {
+    /// The output of the running test.
+    output: PanicMessage<OUTPUT>,
+    failures: [Failure<OUTPUT>; FAILURES],
+    len: usize,
+    /// The number of failures which did not fit in `failures`.
+    dropped: usize,
+}
+
+#[derive(Copy, Clone)]
+struct Failure<const OUTPUT: usize> {
+    metadata: Metadata,
+    output: PanicMessage<OUTPUT>,
+}
+
+impl<const OUTPUT: usize> Failure<OUTPUT> {
+    const EMPTY: Self = Self {
+        metadata: Metadata::new(),
+        output: PanicMessage::new(),
+    };
+}
+
+impl<W, const FAILURES: usize, const OUTPUT: usize> Libtest<W, FAILURES, OUTPUT> {
+    pub const fn new(sink: W) -> Self {
+        Self {
+            inner: Locked::new(
+                sink,
+                Inner {
+                    output: PanicMessage::new(),
+                    failures: [Failure::EMPTY; FAILURES],
+                    len: 0,
+                    dropped: 0,
+                },
+            ),
+        }
+    }
+
+    /// Calls `f` with the sink, e.g. to flush it or to read what has been
+    /// written into it.
+    pub fn with_sink<R, F: FnOnce(&mut W) -> R>(&self, f: F) -> R {
+        self.inner.with_sink(f)
+    }
+}
+
+impl<W: Write + Send, const FAILURES: usize, const OUTPUT: usize> Report for Libtest<W, FAILURES, OUTPUT> {
+    fn run_started(&self, test_count: usize) {
+        let mut inner = self.inner.lock();
+        let plural = if test_count == 1 { "" } else { "s" };
+        writeln!(inner);
+        writeln!(inner, "running {} test{}", test_count, plural);
+    }
+
+    fn test_started(&self, metadata: &Metadata) {
+        let mut inner = self.inner.lock();
+        inner.output = PanicMessage::new();
+        write!(inner, "test {}", TestName(metadata));
+        if metadata.should_fail {
+            write!(inner, " - should panic");
+        }
+        write!(inner, " ... ");
+        if metadata.ignore {
+            match metadata.ignore_message {
+                Some(message) => writeln!(inner, "ignored, {}", message),
+                None => writeln!(inner, "ignored"),
+            }
+        }
+    }
+
+    fn assertion_failed(&self, info: &PanicInfo) {
+        let mut inner = match self.inner.try_lock() {
+            Some(inner) => inner,
+            None => return,
+        };
+        let _ = writeln!(inner.output, "{}", info);
+    }
+
+    fn test_finished(&self, metadata: &Metadata, outcome: &TestOutcome) {
+        let mut inner = self.inner.lock();
+        match *outcome {
+            TestOutcome::Passed => writeln!(inner, "ok"),
+            TestOutcome::Failed { call_stack, note } => {
+                writeln!(inner, "FAILED");
+                let mut output = inner.output;
+                if let Some(note) = note {
+                    let _ = writeln!(output, "note: {}", note);
+                }
+                if let Some(call_stack) = call_stack {
+                    let _ = write!(output, "{}", call_stack);
+                }
+                if inner.len < FAILURES {
+                    let len = inner.len;
+                    inner.failures[len] = Failure {
+                        metadata: *metadata,
+                        output,
+                    };
+                    inner.len += 1;
+                } else {
+                    inner.dropped += 1;
+                }
+            }
+            TestOutcome::Ignored => (),
+        }
+    }
+
+    fn run_finished(&self, summary: &Summary) {
+        let mut inner = self.inner.lock();
+        let inner = &mut *inner;
+        if inner.len > 0 {
+            writeln!(inner);
+            writeln!(inner, "failures:");
+            writeln!(inner);
+            for i in 0..inner.len {
+                let failure = inner.failures[i];
+                if !failure.output.as_str().is_empty() {
+                    writeln!(inner, "---- {} stdout ----", TestName(&failure.metadata));
+                    writeln!(inner, "{}", failure.output);
+                }
+            }
+            writeln!(inner);
+            writeln!(inner, "failures:");
+            for i in 0..inner.len {
+                let metadata = inner.failures[i].metadata;
+                writeln!(inner, "    {}", TestName(&metadata));
+            }
+            if inner.dropped > 0 {
+                let dropped = inner.dropped;
+                writeln!(inner, "    ... and {} more", dropped);
+            }
+        }
+        writeln!(inner);
+        writeln!(
+            inner,
+            "test result: {}. {} passed; {} failed; {} ignored; 0 measured; {} filtered out; finished in 0.00s",
+            if summary.is_ok() { "ok" } else { "FAILED" },
+            summary.passed,
+            summary.failed,
+            summary.ignored,
+            summary.filtered_out,
+        );
+        writeln!(inner);
+        inner.len = 0;
+        inner.dropped = 0;
+    }
+}
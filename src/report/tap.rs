@@ -0,0 +1,178 @@
+use core::fmt::{Display, Formatter, Result as FmtResult, Write};
+
+use crate::panic::{PanicInfo, PanicMessage};
+use crate::runner::Summary;
+use crate::test::Metadata;
+
+use super::{Escaped, Locked, Report, TestOutcome};
+
+/// A reporter writing [TAP version 13](https://testanything.org/tap-version-13-specification.html)
+/// into a [`core::fmt::Write`] sink
+///
+/// Each test is written as soon as it finishes, so nothing but the first
+/// failure of the running test is kept, and a device resetting mid-run leaves
+/// a valid prefix of the stream. As the number of tests selected by the filter
+/// is only known at the end, the plan is written last:
+///
+/// ```text
+/// TAP version 13
+/// ok 1 - uart (loopback)
+/// not ok 2 - uart (baud rate)
+///   ---
+///   message: "assertion failed: `baud == 115200`"
+///   severity: fail
+///   at:
+///     file: "tests/uart.rs"
+///     line: 21
+///   stack:
+///     - "set_baud(9600) at tests/uart.rs:20:5"
+///   ...
+/// ok 3 - flash (erase) # SKIP needs external flash
+/// 1..3
+/// ```
+pub struct Tap<W> {
+    inner: Locked<W, Inner>,
+}
+
+struct Inner {
+    /// The number of tests written so far.
+    count: usize,
+    /// The first failure of the running test.
+    failure: Option<Failure>,
+}
+
+struct Failure {
+    message: PanicMessage,
+    file: PanicMessage,
+    line: u32,
+}
+
+impl<W> Tap<W> {
+    pub const fn new(sink: W) -> Self {
+        Self {
+            inner: Locked::new(sink, Inner { count: 0, failure: None }),
+        }
+    }
+
+    /// Calls `f` with the sink, e.g. to flush it or to read what has been
+    /// written into it.
+    pub fn with_sink<R, F: FnOnce(&mut W) -> R>(&self, f: F) -> R {
+        self.inner.with_sink(f)
+    }
+}
+
+impl<W: Write + Send> Report for Tap<W> {
+    fn run_started(&self, _test_count: usize) {
+        let mut inner = self.inner.lock();
+        inner.count = 0;
+        writeln!(inner, "TAP version 13");
+    }
+
+    fn test_started(&self, metadata: &Metadata) {
+        let mut inner = self.inner.lock();
+        inner.failure = None;
+        if metadata.ignore {
+            inner.count += 1;
+            let count = inner.count;
+            write!(inner, "ok {} - {} # SKIP", count, Description(metadata));
+            match metadata.ignore_message {
+                Some(message) => writeln!(inner, " {}", message),
+                None => writeln!(inner),
+            }
+        }
+    }
+
+    fn assertion_failed(&self, info: &PanicInfo) {
+        let mut inner = match self.inner.try_lock() {
+            Some(inner) => inner,
+            None => return,
+        };
+        if inner.failure.is_none() {
+            let mut failure = Failure {
+                message: PanicMessage::new(),
+                file: PanicMessage::new(),
+                line: info.location.line(),
+            };
+            if let Some(message) = info.message {
+                let _ = write!(failure.message, "{}", message);
+            }
+            let _ = failure.file.write_str(info.location.file());
+            inner.failure = Some(failure);
+        }
+    }
+
+    fn test_finished(&self, metadata: &Metadata, outcome: &TestOutcome) {
+        let mut inner = self.inner.lock();
+        let (call_stack, note) = match *outcome {
+            TestOutcome::Passed => {
+                inner.count += 1;
+                let count = inner.count;
+                writeln!(inner, "ok {} - {}", count, Description(metadata));
+                return;
+            }
+            TestOutcome::Failed { call_stack, note } => (call_stack, note),
+            // Already written when the test started.
+            TestOutcome::Ignored => return,
+        };
+        inner.count += 1;
+        let count = inner.count;
+        writeln!(inner, "not ok {} - {}", count, Description(metadata));
+        writeln!(inner, "  ---");
+        let failure = inner.failure.take();
+        match (&failure, note) {
+            (Some(failure), _) => writeln!(inner, "  message: \"{}\"", escaped(failure.message.as_str())),
+            (None, Some(note)) => writeln!(inner, "  message: \"{}\"", escaped(note)),
+            (None, None) => (),
+        }
+        writeln!(inner, "  severity: fail");
+        if let Some(failure) = &failure {
+            writeln!(inner, "  at:");
+            writeln!(inner, "    file: \"{}\"", escaped(failure.file.as_str()));
+            writeln!(inner, "    line: {}", failure.line);
+        }
+        if let Some(call_stack) = call_stack.filter(|call_stack| !call_stack.is_empty()) {
+            writeln!(inner, "  stack:");
+            for call in call_stack.calls() {
+                writeln!(inner, "    - \"{}\"", escaped(call));
+            }
+            if call_stack.is_truncated() {
+                writeln!(inner, "  truncated: true");
+            }
+        }
+        writeln!(inner, "  ...");
+    }
+
+    fn run_finished(&self, _summary: &Summary) {
+        let mut inner = self.inner.lock();
+        let count = inner.count;
+        writeln!(inner, "1..{}", count);
+    }
+}
+
+/// Writes `target (feature)`, escaping `#` which would start a directive.
+struct Description<'a>(&'a Metadata);
+
+impl Display for Description<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let escaped = Escaped {
+            value: self.0,
+            escape: |c| if c == '#' { Some("\\#") } else { None },
+        };
+        write!(f, "{}", escaped)
+    }
+}
+
+/// Writes a value escaped for a double-quoted YAML string.
+fn escaped<T>(value: T) -> Escaped<T> {
+    Escaped {
+        value,
+        escape: |c| match c {
+            '"' => Some("\\\""),
+            '\\' => Some("\\\\"),
+            '\n' => Some("\\n"),
+            '\r' => Some("\\r"),
+            '\t' => Some("\\t"),
+            _ => None,
+        },
+    }
+}
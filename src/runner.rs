@@ -0,0 +1,712 @@
+//! A ready-made test runner
+//!
+//! Most users of this crate end up writing the same `test_runner` function:
+//! register a reporter, loop over the tests and count the outcomes. [`run`]
+//! does exactly that, writing libtest-style output into any
+//! [`core::fmt::Write`] sink, e.g. a UART wrapper.
+//!
+//! Tests could either be the functions collected by `#[test_case]`, or a
+//! `&'static [TestDescriptor]` built by [`tests!`] on stable Rust.
+//!
+//! [`run_filtered`] only runs the tests matching a [`Filter`], e.g. one
+//! received over UART, and reports the rest as filtered out.
+//!
+//! With feature `std` on Linux, [`run_forked`] runs each test in a child
+//! process, so that a test crashing the process only fails itself, and
+//! [`run_forked_with_reporter`] does so with a reporter.
+//!
+//! [`run_with_reporter`] passes the events of the run to a
+//! [`Report`](../report/trait.Report.html) implementation instead, e.g. one
+//! of the built-in reporters of the [`report`](../report/index.html) module.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use micro_test::backtrace::CallStack;
+//! use micro_test::runner;
+//!
+//! fn test_runner(tests: &[&dyn Fn() -> Result<(), CallStack>]) {
+//!     let mut output = String::new();
+//!     let summary = runner::run(tests, &mut output);
+//!     print!("{}", output);
+//!     if summary.failed != 0 {
+//!         std::process::exit(1);
+//!     }
+//! }
+//! # fn main() { test_runner(&[]); }
+//! ```
+//!
+//! [`run`]: fn.run.html
+//! [`run_filtered`]: fn.run_filtered.html
+//! [`run_forked`]: fn.run_forked.html
+//! [`run_forked_with_reporter`]: fn.run_forked_with_reporter.html
+//! [`run_with_reporter`]: fn.run_with_reporter.html
+//! [`Filter`]: enum.Filter.html
+//! [`tests!`]: ../macro.tests.html
+
+use core::fmt::Write;
+
+use crate::backtrace::CallStack;
+#[cfg(feature = "std")]
+use crate::backtrace::PanicLocation;
+use crate::panic::PanicInfo;
+use crate::report::{self, Report, TestOutcome};
+use crate::sync::Mutex;
+use crate::test::{self, Metadata, TestDescriptor};
+
+#[cfg(all(feature = "std", target_os = "linux"))]
+mod fork;
+
+/// Outcome of a test run
+///
+/// Returned by [`run`](fn.run.html) so that the caller could turn the outcome
+/// into an exit code.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Summary {
+    pub passed: usize,
+    pub failed: usize,
+    pub ignored: usize,
+    pub filtered_out: usize,
+}
+
+impl Summary {
+    /// Returns `true` if no test failed.
+    pub fn is_ok(&self) -> bool {
+        self.failed == 0
+    }
+
+    fn record(&mut self, outcome: Outcome) {
+        match outcome {
+            Outcome::Passed => self.passed += 1,
+            Outcome::Failed => self.failed += 1,
+            Outcome::Ignored => self.ignored += 1,
+            Outcome::FilteredOut => self.filtered_out += 1,
+        }
+    }
+}
+
+/// The outcome of a single test
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Outcome {
+    Passed,
+    Failed,
+    Ignored,
+    FilteredOut,
+}
+
+/// A test that could be run by [`run`](fn.run.html)
+///
+/// Implemented for test functions collected by `#[test_case]` and for
+/// [`TestDescriptor`]s collected by [`tests!`].
+///
+/// [`TestDescriptor`]: ../test/struct.TestDescriptor.html
+/// [`tests!`]: ../macro.tests.html
+pub trait Testable {
+    // The call stack is kept inline, so that relaying needs no allocator.
+    #[allow(clippy::result_large_err)]
+    fn run(&self) -> Result<(), CallStack>;
+}
+
+impl<F: Fn() -> Result<(), CallStack> + ?Sized> Testable for &F {
+    fn run(&self) -> Result<(), CallStack> {
+        self()
+    }
+}
+
+impl Testable for TestDescriptor {
+    fn run(&self) -> Result<(), CallStack> {
+        (self.run)()
+    }
+}
+
+/// A pattern selecting which tests to run
+///
+/// A test is selected if its `target`, `feature` or `module_path` matches the
+/// pattern. Matching needs neither an allocator nor a regex engine.
+///
+/// # Examples
+///
+/// ```rust
+/// use micro_test::runner::Filter;
+///
+/// assert!(Filter::new("crc").matches_str("crc_table"));
+/// assert!(Filter::new("*_table").matches_str("crc_table"));
+/// assert!(!Filter::new("*_table").matches_str("crc_table_init"));
+/// assert!(!Filter::Exact("crc").matches_str("crc_table"));
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Filter<'a> {
+    /// Matches strings equal to the pattern.
+    Exact(&'a str),
+    /// Matches strings containing the pattern.
+    Substring(&'a str),
+    /// Matches strings against the pattern, in which `*` matches any sequence
+    /// of characters.
+    Glob(&'a str),
+}
+
+impl<'a> Filter<'a> {
+    /// Creates a [`Glob`] filter if `pattern` contains `*`, or a
+    /// [`Substring`] filter otherwise.
+    ///
+    /// [`Glob`]: #variant.Glob
+    /// [`Substring`]: #variant.Substring
+    pub fn new(pattern: &'a str) -> Self {
+        if pattern.contains('*') {
+            Filter::Glob(pattern)
+        } else {
+            Filter::Substring(pattern)
+        }
+    }
+
+    /// Checks whether the test with `metadata` is selected.
+    pub fn matches(&self, metadata: &Metadata) -> bool {
+        self.matches_str(metadata.target)
+            || metadata.feature.is_some_and(|feature| self.matches_str(feature))
+            || self.matches_str(metadata.module_path)
+    }
+
+    /// Checks whether every test is selected, without asking the tests for
+    /// their metadata.
+    fn selects_all(&self) -> bool {
+        match *self {
+            Filter::Exact(_) => false,
+            Filter::Substring(pattern) => pattern.is_empty(),
+            Filter::Glob(pattern) => pattern.bytes().all(|c| c == b'*'),
+        }
+    }
+
+    /// Checks whether `s` matches the pattern.
+    pub fn matches_str(&self, s: &str) -> bool {
+        match *self {
+            Filter::Exact(pattern) => s == pattern,
+            Filter::Substring(pattern) => s.contains(pattern),
+            Filter::Glob(pattern) => glob_match(pattern.as_bytes(), s.as_bytes()),
+        }
+    }
+}
+
+/// Matches `s` against `pattern`, backtracking to the last `*` on mismatch.
+fn glob_match(pattern: &[u8], s: &[u8]) -> bool {
+    let (mut p, mut i) = (0, 0);
+    // Position of the last `*` in `pattern`, and where it started matching in `s`
+    let mut star: Option<(usize, usize)> = None;
+    while i < s.len() {
+        if p < pattern.len() && pattern[p] == b'*' {
+            star = Some((p, i));
+            p += 1;
+        } else if p < pattern.len() && pattern[p] == s[i] {
+            p += 1;
+            i += 1;
+        } else if let Some((star_p, star_i)) = star {
+            p = star_p + 1;
+            i = star_i + 1;
+            star = Some((star_p, star_i + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == b'*')
+}
+
+struct State {
+    output: *mut (dyn Write + 'static),
+    /// The reporter events are forwarded to, given to
+    /// [`run_with_reporter`](fn.run_with_reporter.html).
+    reporter: Option<&'static dyn Report>,
+    filter: Filter<'static>,
+    metadata: Metadata,
+    /// Whether `FAILED` has been written for the current test.
+    panicked: bool,
+    /// Whether a `#[micro_should_fail]` test has failed as expected.
+    failed_as_expected: bool,
+    /// Whether the current test has panicked other than expected, which fails
+    /// it even if it has also failed as expected.
+    failed_unexpectedly: bool,
+    /// Whether the current test has been rejected by `filter`.
+    filtered_out: bool,
+    /// Whether tests are only called to count the ones rejected by `filter`.
+    counting: bool,
+    /// The number of tests rejected by `filter` while counting.
+    rejected: usize,
+    /// Where the last real panic of the current test happened.
+    #[cfg(feature = "std")]
+    panic_location: Option<PanicLocation>,
+}
+
+// The output pointer is only dereferenced while holding the lock of `STATE`.
+unsafe impl Send for State {}
+
+impl State {
+    fn write_fmt(&mut self, args: core::fmt::Arguments) {
+        // Errors from the sink can't be reported anywhere, so they are dropped.
+        let _ = unsafe { &mut *self.output }.write_fmt(args);
+    }
+}
+
+static STATE: Mutex<Option<State>> = Mutex::new(None);
+
+fn with_state<F: FnOnce(&mut State)>(f: F) {
+    if let Some(state) = STATE.lock().as_mut() {
+        f(state);
+    }
+}
+
+/// Passes an event to the reporter of the run, if any, without holding the
+/// lock.
+fn forward<F: FnOnce(&dyn Report)>(f: F) {
+    let mut reporter = None;
+    with_state(|state| reporter = state.reporter);
+    if let Some(reporter) = reporter {
+        f(reporter);
+    }
+}
+
+fn filter_metadata(metadata: &Metadata) -> bool {
+    let mut selected = true;
+    with_state(|state| {
+        selected = state.filter.matches(metadata);
+        if state.counting {
+            state.rejected += usize::from(!selected);
+            // Nothing runs while counting.
+            selected = false;
+        } else {
+            state.filtered_out = !selected;
+        }
+    });
+    #[cfg(all(feature = "std", target_os = "linux"))]
+    fork::send_filter(metadata);
+    selected
+}
+
+/// The reporter registered by the runner, which writes into the output of the
+/// current run and forwards events to the reporter of the run
+struct Runner;
+
+impl Report for Runner {
+    fn run_started(&self, test_count: usize) {
+        with_state(|state| {
+            writeln!(state);
+            writeln!(state, "running {} tests", test_count);
+        });
+        forward(|reporter| reporter.run_started(test_count));
+    }
+
+    fn test_started(&self, metadata: &Metadata) {
+        #[cfg(all(feature = "std", target_os = "linux"))]
+        if fork::send_started(metadata) {
+            return;
+        }
+        with_state(|state| {
+            state.metadata = *metadata;
+            write!(state, "test {} ... ", metadata);
+            if metadata.ignore {
+                match metadata.ignore_message {
+                    Some(message) => writeln!(state, "ignored, {}", message),
+                    None => writeln!(state, "ignored"),
+                }
+            }
+        });
+        forward(|reporter| reporter.test_started(metadata));
+    }
+
+    /// The lock is only tried, as a real panic may come from the output sink
+    /// while the lock is held.
+    fn assertion_failed(&self, info: &PanicInfo) {
+        #[cfg(all(feature = "std", target_os = "linux"))]
+        if fork::send_panic(info) {
+            return;
+        }
+        let mut reporter = None;
+        if let Some(state) = STATE.try_lock().as_mut().and_then(|state| state.as_mut()) {
+            if report_panic(state, info) {
+                reporter = state.reporter;
+            }
+        }
+        if let Some(reporter) = reporter {
+            reporter.assertion_failed(info);
+        }
+    }
+
+    fn test_finished(&self, metadata: &Metadata, outcome: &TestOutcome) {
+        with_state(|state| match *outcome {
+            TestOutcome::Passed => writeln!(state, "ok"),
+            TestOutcome::Failed { call_stack, note } => {
+                if !state.panicked {
+                    writeln!(state, "FAILED");
+                }
+                if let Some(note) = note {
+                    writeln!(state, "    note: {}", note);
+                }
+                if let Some(call_stack) = call_stack {
+                    for (i, call) in call_stack.calls().iter().enumerate() {
+                        writeln!(state, "    #{}: {}", i, call);
+                    }
+                    if call_stack.is_truncated() {
+                        writeln!(state, "    ... truncated after {} frames", call_stack.len());
+                    }
+                }
+            }
+            // Already written when the test started.
+            TestOutcome::Ignored => (),
+        });
+        forward(|reporter| reporter.test_finished(metadata, outcome));
+    }
+
+    fn run_finished(&self, summary: &Summary) {
+        with_state(|state| {
+            writeln!(state);
+            writeln!(
+                state,
+                "test result: {}. {} passed; {} failed; {} ignored; {} filtered out",
+                if summary.is_ok() { "ok" } else { "FAILED" },
+                summary.passed,
+                summary.failed,
+                summary.ignored,
+                summary.filtered_out,
+            );
+        });
+        forward(|reporter| reporter.run_finished(summary));
+    }
+}
+
+/// Writes a panic, and returns whether it fails the test, i.e. it is not the
+/// failure expected by a `#[micro_should_fail]` test.
+fn report_panic(state: &mut State, info: &PanicInfo) -> bool {
+    if state.metadata.should_fail {
+        let matched = match state.metadata.expected {
+            Some(expected) => info.message_contains(expected),
+            None => true,
+        };
+        if matched {
+            state.failed_as_expected = true;
+            return false;
+        }
+    }
+    state.failed_unexpectedly = true;
+    if !state.panicked {
+        state.panicked = true;
+        writeln!(state, "FAILED");
+    }
+    writeln!(state, "    {}", info);
+    if let Some(backtrace) = info.backtrace {
+        writeln!(state, "    backtrace:");
+        for (i, frame) in backtrace.frames().iter().enumerate() {
+            writeln!(state, "      #{}: {:#018x}", i, frame.ip());
+        }
+    }
+    if let Some(expected) = state.metadata.expected {
+        writeln!(state, "    note: panic did not contain expected string `{}`", expected);
+    }
+    true
+}
+
+/// Reports a real panic, e.g. from `unwrap()`, like a `micro_panic!`
+#[cfg(feature = "std")]
+fn handle_real_panic(hook_info: &std::panic::PanicHookInfo) {
+    let message = payload_message(hook_info.payload());
+    let location = match hook_info.location() {
+        Some(location) => location,
+        None => return,
+    };
+    // The state is busy if the panic comes from the runner itself.
+    if let Some(Some(state)) = STATE.try_lock().as_deref_mut() {
+        state.panic_location = Some(PanicLocation::new(
+            static_file(location.file()),
+            location.line(),
+            location.column(),
+        ));
+    }
+    report::reporter().assertion_failed(&PanicInfo {
+        message: Some(&format_args!("{}", message)),
+        location,
+        assertion: None,
+        backtrace: None,
+    });
+}
+
+/// Returns `file` as a `&'static str`, leaking each file name only once, as
+/// real panics only lend their location.
+#[cfg(feature = "std")]
+fn static_file(file: &str) -> &'static str {
+    static FILES: Mutex<Vec<&'static str>> = Mutex::new(Vec::new());
+    let mut files = FILES.lock();
+    match files.iter().find(|&&known| known == file) {
+        Some(known) => known,
+        None => {
+            let file = &*Box::leak(Box::from(file));
+            files.push(file);
+            file
+        }
+    }
+}
+
+#[cfg(not(feature = "std"))]
+#[allow(clippy::result_large_err)]
+fn run_test<T: Testable>(test: &T) -> Result<(), CallStack> {
+    test.run()
+}
+
+/// Runs `test` in this process and reports its outcome.
+fn run_in_process<T: Testable>(test: &T, _index: usize) -> Outcome {
+    finish_test(run_test(test))
+}
+
+fn finish_test(result: Result<(), CallStack>) -> Outcome {
+    let (mut metadata, mut filtered_out, mut failed_as_expected) = (Metadata::new(), false, false);
+    with_state(|state| {
+        metadata = state.metadata;
+        filtered_out = state.filtered_out;
+        failed_as_expected = state.failed_as_expected && !state.failed_unexpectedly;
+    });
+    let (outcome, test_outcome) = match &result {
+        _ if filtered_out => (Outcome::FilteredOut, None),
+        _ if metadata.ignore => (Outcome::Ignored, Some(TestOutcome::Ignored)),
+        Err(_) if failed_as_expected => (Outcome::Passed, Some(TestOutcome::Passed)),
+        Ok(()) if metadata.should_fail => (
+            Outcome::Failed,
+            Some(TestOutcome::Failed {
+                call_stack: None,
+                note: Some("test did not fail as expected"),
+            }),
+        ),
+        Ok(()) => (Outcome::Passed, Some(TestOutcome::Passed)),
+        Err(call_stack) => (
+            Outcome::Failed,
+            Some(TestOutcome::Failed {
+                call_stack: Some(call_stack),
+                note: None,
+            }),
+        ),
+    };
+    if let Some(test_outcome) = test_outcome {
+        report::reporter().test_finished(&metadata, &test_outcome);
+    }
+    reset_test();
+    outcome
+}
+
+/// Fails the current test with `note`, e.g. one which crashed the process
+/// running it.
+#[cfg(all(feature = "std", target_os = "linux"))]
+fn fail_test(note: &str) -> Outcome {
+    let mut metadata = Metadata::new();
+    with_state(|state| metadata = state.metadata);
+    let outcome = TestOutcome::Failed {
+        call_stack: None,
+        note: Some(note),
+    };
+    report::reporter().test_finished(&metadata, &outcome);
+    reset_test();
+    Outcome::Failed
+}
+
+fn reset_test() {
+    with_state(|state| {
+        state.metadata = Metadata::new();
+        state.panicked = false;
+        state.failed_as_expected = false;
+        state.failed_unexpectedly = false;
+        state.filtered_out = false;
+    });
+}
+
+/// Runs `test`, turning a real panic into a failure with an empty call stack
+/// holding the panic message and location.
+#[cfg(feature = "std")]
+#[allow(clippy::result_large_err)]
+fn run_test<T: Testable>(test: &T) -> Result<(), CallStack> {
+    std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| test.run())).unwrap_or_else(|payload| {
+        let message = format_args!("{}", payload_message(&*payload));
+        let mut location = None;
+        with_state(|state| location = state.panic_location.take());
+        Err(CallStack::from_panic(crate::panic::PanicMessage::from_args(&message), location))
+    })
+}
+
+#[cfg(feature = "std")]
+fn payload_message(payload: &(dyn std::any::Any + Send)) -> &str {
+    match payload.downcast_ref::<&str>() {
+        Some(message) => message,
+        None => match payload.downcast_ref::<String>() {
+            Some(message) => message.as_str(),
+            None => "Box<dyn Any>",
+        },
+    }
+}
+
+/// Runs `tests` and writes the report into `output`
+///
+/// The output mimics libtest: a `running N tests` header, one
+/// `test target (feature) ... ok|FAILED|ignored` line per test, followed by the panic
+/// message and relayed call stack of failed tests, and a final
+/// `test result: ...` summary line.
+///
+/// With feature `std`, each test is run in `catch_unwind`, so that a real
+/// panic, e.g. from `unwrap()`, fails the test with its message and location
+/// like a `micro_panic!` does, and the run moves on to the next test. The
+/// panic hook is replaced during the run.
+///
+/// # Panics
+///
+/// This function registers its own reporter and filter, thus it will panic if
+/// either of them has already been set, including by a previous call to `run`
+/// or [`run_filtered`](fn.run_filtered.html).
+pub fn run<T: Testable, W: Write>(tests: &[T], output: &mut W) -> Summary {
+    run_filtered(tests, Filter::Substring(""), output)
+}
+
+/// Runs the tests in `tests` selected by `filter`
+///
+/// Same as [`run`](fn.run.html), except that tests not matching `filter` are
+/// skipped and counted as filtered out, and are not counted in the
+/// `running N tests` header.
+///
+/// As the metadata of a test is only known once it is called, every test is
+/// called once before the run with the filter rejecting it, so that the
+/// selected tests could be counted. `#[micro_test_case]` functions return
+/// right away then, while other test functions would run twice.
+pub fn run_filtered<T: Testable, W: Write>(tests: &[T], filter: Filter, output: &mut W) -> Summary {
+    run_with(tests, filter, output, None, run_in_process)
+}
+
+/// Runs the tests in `tests` selected by `filter`, passing the events to
+/// `reporter` instead of writing the output of [`run`](fn.run.html)
+///
+/// The runner still decides the outcome of each test, e.g. a failure expected
+/// by `#[micro_should_fail]` is not passed to `reporter` and the test is
+/// reported as passed.
+///
+/// # Examples
+///
+/// ```rust
+/// use micro_test::backtrace::CallStack;
+/// use micro_test::report::Libtest;
+/// use micro_test::runner::{self, Filter};
+///
+/// static LIBTEST: Libtest<String> = Libtest::new(String::new());
+///
+/// fn test_runner(tests: &[&dyn Fn() -> Result<(), CallStack>]) {
+///     let summary = runner::run_with_reporter(tests, Filter::Substring(""), &LIBTEST);
+///     LIBTEST.with_sink(|output| print!("{}", output));
+///     assert!(summary.is_ok());
+/// }
+/// # fn main() { test_runner(&[]); }
+/// ```
+pub fn run_with_reporter<T: Testable>(tests: &[T], filter: Filter, reporter: &'static dyn Report) -> Summary {
+    run_with(tests, filter, &mut Discard, Some(reporter), run_in_process)
+}
+
+/// A sink dropping the output of the runner
+struct Discard;
+
+impl Write for Discard {
+    fn write_str(&mut self, _s: &str) -> core::fmt::Result {
+        Ok(())
+    }
+}
+
+/// Runs every test selected by `filter` in a forked child process
+///
+/// Same as [`run_filtered`](fn.run_filtered.html), except that the events of
+/// each test, i.e. its metadata, panics and the call stack it returned, are
+/// sent back over a pipe and replayed in this process. A test which aborts,
+/// is killed by a signal, e.g. on a segfault in FFI code, or exits the process
+/// fails with a note giving the signal or the exit code, and the run moves on
+/// to the next test.
+#[cfg(all(feature = "std", target_os = "linux"))]
+pub fn run_forked<T: Testable, W: Write>(tests: &[T], filter: Filter, output: &mut W) -> Summary {
+    run_with(tests, filter, output, None, fork::run_in_child)
+}
+
+/// Runs every test selected by `filter` in a forked child process, passing
+/// the events to `reporter`
+///
+/// Combines [`run_forked`](fn.run_forked.html) and
+/// [`run_with_reporter`](fn.run_with_reporter.html): a crashed test is passed
+/// to `reporter` as failed, with the signal or the exit code as note.
+#[cfg(all(feature = "std", target_os = "linux"))]
+pub fn run_forked_with_reporter<T: Testable>(tests: &[T], filter: Filter, reporter: &'static dyn Report) -> Summary {
+    run_with(tests, filter, &mut Discard, Some(reporter), fork::run_in_child)
+}
+
+fn run_with<T: Testable, W: Write>(
+    tests: &[T],
+    filter: Filter,
+    output: &mut W,
+    reporter: Option<&'static dyn Report>,
+    run_one: fn(&T, usize) -> Outcome,
+) -> Summary {
+    #[cfg(feature = "spin_once")]
+    {
+        report::set_reporter(&Runner);
+        test::set_metadata_filter(filter_metadata);
+    }
+    // Tests are only run once the reporter and filter are set.
+    #[cfg(feature = "racy")]
+    unsafe {
+        report::set_reporter_racy(&Runner);
+        test::set_metadata_filter_racy(filter_metadata);
+    }
+
+    let output: *mut (dyn Write + '_) = output;
+    // Lifetimes are erased here and restored by clearing `STATE` when
+    // `_clear` is dropped, even on unwinding, so neither the output pointer
+    // nor the filter pattern outlive their borrows.
+    let output = unsafe {
+        core::mem::transmute::<*mut (dyn Write + '_), *mut (dyn Write + 'static)>(output)
+    };
+    let filter = unsafe { core::mem::transmute::<Filter<'_>, Filter<'static>>(filter) };
+    *STATE.lock() = Some(State {
+        output,
+        reporter,
+        filter,
+        metadata: Metadata::new(),
+        panicked: false,
+        failed_as_expected: false,
+        failed_unexpectedly: false,
+        filtered_out: false,
+        counting: false,
+        rejected: 0,
+        #[cfg(feature = "std")]
+        panic_location: None,
+    });
+    let _clear = ClearState;
+
+    let mut test_count = tests.len();
+    if !filter.selects_all() {
+        // Tests only give their metadata to the filter when called, and
+        // return right away while counting.
+        with_state(|state| state.counting = true);
+        for test in tests {
+            let _ = test.run();
+        }
+        with_state(|state| {
+            state.counting = false;
+            test_count -= state.rejected;
+        });
+    }
+
+    let mut summary = Summary::default();
+    report::reporter().run_started(test_count);
+    #[cfg(feature = "std")]
+    let default_hook = std::panic::take_hook();
+    #[cfg(feature = "std")]
+    std::panic::set_hook(Box::new(handle_real_panic));
+    for (index, test) in tests.iter().enumerate() {
+        summary.record(run_one(test, index));
+    }
+    #[cfg(feature = "std")]
+    std::panic::set_hook(default_hook);
+    report::reporter().run_finished(&summary);
+    summary
+}
+
+/// Clears `STATE` when dropped
+struct ClearState;
+
+impl Drop for ClearState {
+    fn drop(&mut self) {
+        *STATE.lock() = None;
+    }
+}
@@ -0,0 +1,471 @@
+//! Fork-per-test isolation for [`run_forked`](../fn.run_forked.html)
+//!
+//! The child runs the test, and instead of handling the events of its runner,
+//! sends them to the parent, which replays them to its own runner, and thus
+//! to the reporter of the run. Records sent over the pipe start with a tag
+//! byte and a little-endian `u32` length:
+//!
+//! - [`TAG_FILTER`]: the [`Metadata`] of a test passed to the filter.
+//! - [`TAG_STARTED`]: the [`Metadata`] of a test which has started.
+//! - [`TAG_PANIC`]: a [`PanicInfo`], whose message and assertion values are
+//!   rendered.
+//! - [`TAG_RESULT`]: the result of the test, with the frames of its
+//!   [`CallStack`] if it failed, sent last.
+//!
+//! A child which exits without sending its result has failed.
+//!
+//! Locations and the `&'static str`s of metadata and frames, which reporters
+//! may keep until the run finishes, are sent as addresses, which are the same
+//! in the parent as the child is a copy of it. Other strings, e.g. rendered
+//! messages, are sent as UTF-8, and the file of a panic location is interned
+//! in the parent, as a real panic only lends it.
+
+use core::fmt::{Debug, Formatter, Result as FmtResult};
+use core::panic::Location;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::os::unix::io::FromRawFd;
+
+use super::{finish_test, report, run_test, with_state, Outcome, Testable};
+use crate::backtrace::{Backtrace, CallStack, FuncArgs, FuncCall, PanicLocation};
+use crate::panic::{AssertionFailure, AssertionKind, PanicInfo, PanicMessage};
+use crate::sync::Mutex;
+use crate::test::Metadata;
+
+const TAG_FILTER: u8 = 0;
+const TAG_STARTED: u8 = 1;
+const TAG_PANIC: u8 = 2;
+const TAG_RESULT: u8 = 3;
+
+const ASSERTION_KINDS: [AssertionKind; 4] = [
+    AssertionKind::Bool,
+    AssertionKind::Eq,
+    AssertionKind::Ne,
+    AssertionKind::Custom,
+];
+
+/// The pipe to the parent, in a child only.
+static PARENT: Mutex<Option<File>> = Mutex::new(None);
+
+/// Sends a record to the parent, and returns whether this is a child.
+///
+/// The lock is only tried, as a real panic may come from encoding a record,
+/// e.g. from the `Debug` implementation of an assertion value.
+fn send<F: FnOnce(&mut Encoder)>(tag: u8, encode: F) -> bool {
+    let mut parent = match PARENT.try_lock() {
+        Some(parent) => parent,
+        None => return true,
+    };
+    let pipe = match parent.as_mut() {
+        Some(pipe) => pipe,
+        None => return false,
+    };
+    let mut encoder = Encoder(vec![tag, 0, 0, 0, 0]);
+    encode(&mut encoder);
+    let len = (encoder.0.len() - 5) as u32;
+    encoder.0[1..5].copy_from_slice(&len.to_le_bytes());
+    // The parent fails the test if the record is lost.
+    let _ = pipe.write_all(&encoder.0);
+    true
+}
+
+/// Sends the metadata passed to the filter, if this is a child.
+pub(super) fn send_filter(metadata: &Metadata) {
+    send(TAG_FILTER, |encoder| encoder.metadata(metadata));
+}
+
+/// Sends the metadata of a started test, and returns whether this is a child.
+pub(super) fn send_started(metadata: &Metadata) -> bool {
+    send(TAG_STARTED, |encoder| encoder.metadata(metadata))
+}
+
+/// Sends a panic, and returns whether this is a child.
+pub(super) fn send_panic(info: &PanicInfo) -> bool {
+    send(TAG_PANIC, |encoder| encoder.panic_info(info))
+}
+
+/// Receives a record, or `None` once the child has closed the pipe.
+fn receive(pipe: &mut File, payload: &mut Vec<u8>) -> Option<u8> {
+    let mut header = [0; 5];
+    pipe.read_exact(&mut header).ok()?;
+    let mut len = [0; 4];
+    len.copy_from_slice(&header[1..]);
+    payload.resize(u32::from_le_bytes(len) as usize, 0);
+    pipe.read_exact(payload).ok()?;
+    Some(header[0])
+}
+
+pub(super) fn run_in_child<T: Testable>(test: &T, index: usize) -> Outcome {
+    let mut fds = [0; 2];
+    if unsafe { libc::pipe(fds.as_mut_ptr()) } != 0 {
+        panic!("cannot create pipe: {}", io::Error::last_os_error());
+    }
+    let (read_fd, write_fd) = (fds[0], fds[1]);
+    match unsafe { libc::fork() } {
+        -1 => {
+            let error = io::Error::last_os_error();
+            unsafe {
+                libc::close(read_fd);
+                libc::close(write_fd);
+            }
+            panic!("cannot fork: {}", error)
+        }
+        0 => {
+            unsafe { libc::close(read_fd) };
+            *PARENT.lock() = Some(unsafe { File::from_raw_fd(write_fd) });
+            let result = run_test(test);
+            send(TAG_RESULT, |encoder| encoder.result(&result));
+            // Exits right away, so that neither the rest of the suite nor
+            // any destructor runs in the child.
+            unsafe { libc::_exit(0) }
+        }
+        pid => {
+            unsafe { libc::close(write_fd) };
+            let mut pipe = unsafe { File::from_raw_fd(read_fd) };
+            wait_for_child(pid, &mut pipe, index)
+        }
+    }
+}
+
+fn wait_for_child(pid: libc::pid_t, pipe: &mut File, index: usize) -> Outcome {
+    let mut result = None;
+    let mut started = false;
+    let mut payload = Vec::new();
+    while let Some(tag) = receive(pipe, &mut payload) {
+        let mut decoder = Decoder(&payload);
+        match tag {
+            TAG_FILTER => {
+                if let Some(metadata) = decoder.metadata() {
+                    super::filter_metadata(&metadata);
+                }
+            }
+            TAG_STARTED => {
+                if let Some(metadata) = decoder.metadata() {
+                    started = true;
+                    report::reporter().test_started(&metadata);
+                }
+            }
+            TAG_PANIC => {
+                decoder.panic_info(|info| report::reporter().assertion_failed(info));
+            }
+            TAG_RESULT => result = decoder.result(),
+            _ => (),
+        }
+    }
+
+    let mut status = 0;
+    while unsafe { libc::waitpid(pid, &mut status, 0) } == -1 {
+        if io::Error::last_os_error().kind() != io::ErrorKind::Interrupted {
+            break;
+        }
+    }
+    if let Some(result) = result {
+        return finish_test(result);
+    }
+    if !started {
+        with_state(|state| write!(state, "test #{} ... ", index));
+    }
+    let note = if libc::WIFSIGNALED(status) {
+        let signal = libc::WTERMSIG(status);
+        match signal_name(signal) {
+            Some(name) => format!("test terminated by signal {} ({})", signal, name),
+            None => format!("test terminated by signal {}", signal),
+        }
+    } else {
+        format!("test exited with code {}", libc::WEXITSTATUS(status))
+    };
+    super::fail_test(&note)
+}
+
+fn signal_name(signal: libc::c_int) -> Option<&'static str> {
+    match signal {
+        libc::SIGABRT => Some("SIGABRT"),
+        libc::SIGBUS => Some("SIGBUS"),
+        libc::SIGFPE => Some("SIGFPE"),
+        libc::SIGILL => Some("SIGILL"),
+        libc::SIGKILL => Some("SIGKILL"),
+        libc::SIGSEGV => Some("SIGSEGV"),
+        libc::SIGTERM => Some("SIGTERM"),
+        libc::SIGTRAP => Some("SIGTRAP"),
+        _ => None,
+    }
+}
+
+/// Encodes the payload of a record
+struct Encoder(Vec<u8>);
+
+impl Encoder {
+    fn u32(&mut self, value: u32) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn u64(&mut self, value: u64) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn bool(&mut self, value: bool) {
+        self.0.push(u8::from(value));
+    }
+
+    fn str(&mut self, s: &str) {
+        self.u32(s.len() as u32);
+        self.0.extend_from_slice(s.as_bytes());
+    }
+
+    fn static_str(&mut self, s: &'static str) {
+        self.u64(s.as_ptr() as u64);
+        self.u32(s.len() as u32);
+    }
+
+    fn option<T, F: FnOnce(&mut Self, T)>(&mut self, value: Option<T>, encode: F) {
+        self.bool(value.is_some());
+        if let Some(value) = value {
+            encode(self, value);
+        }
+    }
+
+    fn location(&mut self, location: &Location) {
+        self.u64(location as *const Location as u64);
+    }
+
+    fn panic_location(&mut self, location: PanicLocation) {
+        self.str(location.file());
+        self.u32(location.line());
+        self.u32(location.column());
+    }
+
+    fn metadata(&mut self, metadata: &Metadata) {
+        self.static_str(metadata.target);
+        self.option(metadata.feature, Self::static_str);
+        self.static_str(metadata.name);
+        self.static_str(metadata.file);
+        self.u32(metadata.line);
+        self.static_str(metadata.module_path);
+        self.bool(metadata.ignore);
+        self.option(metadata.ignore_message, Self::static_str);
+        self.bool(metadata.should_fail);
+        self.option(metadata.expected, Self::static_str);
+    }
+
+    fn panic_info(&mut self, info: &PanicInfo) {
+        self.option(info.message, |encoder, message| encoder.str(&message.to_string()));
+        self.location(info.location);
+        self.option(info.assertion, |encoder, assertion| {
+            encoder.0.push(assertion.kind as u8);
+            encoder.str(assertion.left_expr);
+            encoder.option(assertion.right_expr, Self::str);
+            encoder.option(assertion.left, |encoder, left| encoder.str(&format!("{:?}", left)));
+            encoder.option(assertion.right, |encoder, right| encoder.str(&format!("{:?}", right)));
+            encoder.option(assertion.message, |encoder, message| encoder.str(&message.to_string()));
+        });
+        self.option(info.backtrace, |encoder, backtrace| {
+            encoder.bool(backtrace.is_truncated());
+            encoder.u32(backtrace.len() as u32);
+            for frame in backtrace.frames() {
+                encoder.u64(frame.ip() as u64);
+                encoder.u64(frame.fp() as u64);
+            }
+        });
+    }
+
+    fn result(&mut self, result: &Result<(), CallStack>) {
+        self.option(result.as_ref().err(), |encoder, call_stack| {
+            encoder.option(call_stack.message(), |encoder, message| {
+                encoder.str(message.as_str());
+                encoder.bool(message.is_truncated());
+            });
+            encoder.option(call_stack.location(), Self::panic_location);
+            encoder.bool(call_stack.is_truncated());
+            encoder.u32(call_stack.len() as u32);
+            for call in call_stack.calls() {
+                encoder.static_str(call.name);
+                encoder.static_str(call.file);
+                encoder.u32(call.line);
+                encoder.u32(call.column);
+                encoder.option(call.receiver, Self::static_str);
+                encoder.option(call.args.as_ref(), |encoder, args| {
+                    encoder.str(args.as_str());
+                    encoder.bool(args.is_truncated());
+                });
+            }
+        });
+    }
+}
+
+/// Decodes the payload of a record, returning `None` if it is cut short
+struct Decoder<'a>(&'a [u8]);
+
+impl<'a> Decoder<'a> {
+    fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        if self.0.len() < len {
+            return None;
+        }
+        let (bytes, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Some(bytes)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.bytes(1).map(|bytes| bytes[0])
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        let mut value = [0; 4];
+        value.copy_from_slice(self.bytes(4)?);
+        Some(u32::from_le_bytes(value))
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        let mut value = [0; 8];
+        value.copy_from_slice(self.bytes(8)?);
+        Some(u64::from_le_bytes(value))
+    }
+
+    fn bool(&mut self) -> Option<bool> {
+        self.u8().map(|value| value != 0)
+    }
+
+    fn str(&mut self) -> Option<&'a str> {
+        let len = self.u32()? as usize;
+        core::str::from_utf8(self.bytes(len)?).ok()
+    }
+
+    fn static_str(&mut self) -> Option<&'static str> {
+        let address = self.u64()? as usize;
+        let len = self.u32()? as usize;
+        // Safety: as for locations, the address and length are the ones of a
+        // `&'static str` in the child.
+        Some(unsafe { core::str::from_utf8_unchecked(core::slice::from_raw_parts(address as *const u8, len)) })
+    }
+
+    /// Decodes an option, whose value is `Some(None)` if it is cut short.
+    fn option<T, F: FnOnce(&mut Self) -> Option<T>>(&mut self, decode: F) -> Option<Option<T>> {
+        match self.bool()? {
+            true => decode(self).map(Some),
+            false => Some(None),
+        }
+    }
+
+    fn location(&mut self) -> Option<&'static Location<'static>> {
+        let address = self.u64()? as usize;
+        // Safety: the address is the one of a location in the child, which is
+        // a copy of this process.
+        Some(unsafe { &*(address as *const Location<'static>) })
+    }
+
+    fn panic_location(&mut self) -> Option<PanicLocation> {
+        Some(PanicLocation::new(super::static_file(self.str()?), self.u32()?, self.u32()?))
+    }
+
+    fn metadata(&mut self) -> Option<Metadata> {
+        Some(Metadata {
+            target: self.static_str()?,
+            feature: self.option(Self::static_str)?,
+            name: self.static_str()?,
+            file: self.static_str()?,
+            line: self.u32()?,
+            module_path: self.static_str()?,
+            ignore: self.bool()?,
+            ignore_message: self.option(Self::static_str)?,
+            should_fail: self.bool()?,
+            expected: self.option(Self::static_str)?,
+        })
+    }
+
+    /// Calls `f` with the decoded panic, unless it is cut short.
+    fn panic_info<F: FnOnce(&PanicInfo)>(&mut self, f: F) -> Option<()> {
+        let message = self.option(Self::str)?;
+        let location = self.location()?;
+        let assertion = self.option(|decoder| {
+            Some((
+                *ASSERTION_KINDS.get(decoder.u8()? as usize)?,
+                decoder.str()?,
+                decoder.option(Self::str)?,
+                decoder.option(Self::str)?,
+                decoder.option(Self::str)?,
+                decoder.option(Self::str)?,
+            ))
+        })?;
+        let backtrace = self.option(|decoder| {
+            let truncated = decoder.bool()?;
+            let mut frames = Vec::new();
+            for _ in 0..decoder.u32()? {
+                frames.push((decoder.u64()? as usize, decoder.u64()? as usize));
+            }
+            Some(Backtrace::from_frames(frames, truncated))
+        })?;
+
+        let message_args = format_args!("{}", message.unwrap_or_default());
+        let (left, right) = match assertion {
+            Some((_, _, _, left, right, _)) => (left.map(Rendered), right.map(Rendered)),
+            None => (None, None),
+        };
+        let assertion_message = assertion.and_then(|(_, _, _, _, _, message)| message);
+        let assertion_message_args = format_args!("{}", assertion_message.unwrap_or_default());
+        let assertion = assertion.map(|(kind, left_expr, right_expr, ..)| AssertionFailure {
+            kind,
+            left_expr,
+            right_expr,
+            left: left.as_ref().map(|left| left as &dyn Debug),
+            right: right.as_ref().map(|right| right as &dyn Debug),
+            message: assertion_message.map(|_| &assertion_message_args),
+        });
+        f(&PanicInfo {
+            message: message.map(|_| &message_args),
+            location,
+            assertion: assertion.as_ref(),
+            backtrace: backtrace.as_ref(),
+        });
+        Some(())
+    }
+
+    fn result(&mut self) -> Option<Result<(), CallStack>> {
+        let call_stack = self.option(|decoder| {
+            let message = decoder.option(|decoder| {
+                let mut message = PanicMessage::from_args(&format_args!("{}", decoder.str()?));
+                if decoder.bool()? {
+                    message.set_truncated();
+                }
+                Some(message)
+            })?;
+            let location = decoder.option(Self::panic_location)?;
+            let mut call_stack = match message {
+                Some(message) => CallStack::from_panic(message, location),
+                None => CallStack::new(),
+            };
+            if decoder.bool()? {
+                call_stack.set_truncated();
+            }
+            for _ in 0..decoder.u32()? {
+                call_stack.push(FuncCall {
+                    name: decoder.static_str()?,
+                    file: decoder.static_str()?,
+                    line: decoder.u32()?,
+                    column: decoder.u32()?,
+                    receiver: decoder.option(Self::static_str)?,
+                    args: decoder.option(|decoder| {
+                        let mut args = FuncArgs::from_debug(&[&Rendered(decoder.str()?)]);
+                        if decoder.bool()? {
+                            args.set_truncated();
+                        }
+                        Some(args)
+                    })?,
+                });
+            }
+            Some(call_stack)
+        })?;
+        Some(match call_stack {
+            Some(call_stack) => Err(call_stack),
+            None => Ok(()),
+        })
+    }
+}
+
+/// A value rendered with `Debug` in the child
+struct Rendered<'a>(&'a str);
+
+impl Debug for Rendered<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_str(self.0)
+    }
+}
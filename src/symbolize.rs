@@ -0,0 +1,178 @@
+//! Host-side symbolization of backtrace addresses
+//!
+//! Devices could only print the raw return addresses of a
+//! [`Backtrace`](../backtrace/struct.Backtrace.html), as in the
+//! `#index: 0xaddress` lines written by the runner. A [`Symbolizer`] built
+//! from the ELF of the program rewrites them into `function (file:line)`,
+//! with the function taken from the symbol table and the location from the
+//! DWARF line info.
+//!
+//! This is what the `micro-test-symbolize` binary does to a log:
+//!
+//! ```text
+//! micro-test-symbolize firmware.elf [log] [--bias 0xoffset]
+//! ```
+//!
+//! [`Symbolizer`]: struct.Symbolizer.html
+
+use std::fmt::{self, Display, Formatter};
+
+use addr2line::gimli;
+use object::{Object, ObjectSection, ObjectSymbol, ObjectSymbolTable};
+
+type Reader<'data> = gimli::EndianSlice<'data, gimli::RunTimeEndian>;
+
+/// An error while reading the ELF
+#[derive(Debug)]
+pub enum Error {
+    Object(object::Error),
+    Dwarf(gimli::Error),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Object(e) => write!(f, "cannot read ELF: {}", e),
+            Error::Dwarf(e) => write!(f, "cannot read DWARF: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<object::Error> for Error {
+    fn from(e: object::Error) -> Self {
+        Error::Object(e)
+    }
+}
+
+impl From<gimli::Error> for Error {
+    fn from(e: gimli::Error) -> Self {
+        Error::Dwarf(e)
+    }
+}
+
+/// The function and source location of an address
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Symbol<'data> {
+    pub function: Option<String>,
+    pub file: Option<&'data str>,
+    pub line: Option<u32>,
+}
+
+/// Writes `function (file:line)`, leaving out whatever is unknown.
+impl Display for Symbol<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.function.as_deref().unwrap_or("??"))?;
+        if let Some(file) = self.file {
+            match self.line {
+                Some(line) => write!(f, " ({}:{})", file, line)?,
+                None => write!(f, " ({})", file)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Resolves addresses with the symbol table and DWARF line info of an ELF
+pub struct Symbolizer<'data> {
+    symbols: object::SymbolMap<object::SymbolMapName<'data>>,
+    context: addr2line::Context<Reader<'data>>,
+    bias: u64,
+}
+
+impl<'data> Symbolizer<'data> {
+    /// Parses `elf`, whose addresses are those printed by the device.
+    pub fn new(elf: &'data [u8]) -> Result<Self, Error> {
+        Self::with_bias(elf, 0)
+    }
+
+    /// Parses `elf`, which has been loaded `bias` bytes above its link
+    /// address, e.g. a position-independent executable on the host.
+    pub fn with_bias(elf: &'data [u8], bias: u64) -> Result<Self, Error> {
+        let file = object::File::parse(elf)?;
+        let endian = if file.is_little_endian() {
+            gimli::RunTimeEndian::Little
+        } else {
+            gimli::RunTimeEndian::Big
+        };
+        let dwarf = gimli::Dwarf::load(|id| -> Result<Reader<'data>, gimli::Error> {
+            let data = file
+                .section_by_name(id.name())
+                .and_then(|section| section.data().ok())
+                .unwrap_or(&[]);
+            Ok(gimli::EndianSlice::new(data, endian))
+        })?;
+        let symbols = match file.symbol_table() {
+            Some(table) => object::SymbolMap::new(
+                table
+                    .symbols()
+                    .filter(|symbol| symbol.kind() == object::SymbolKind::Text)
+                    .filter_map(|symbol| {
+                        let name = symbol.name().ok()?;
+                        Some(object::SymbolMapName::new(symbol.address(), name))
+                    })
+                    .collect(),
+            ),
+            None => file.symbol_map(),
+        };
+        Ok(Symbolizer {
+            symbols,
+            context: addr2line::Context::from_dwarf(dwarf)?,
+            bias,
+        })
+    }
+
+    /// Resolves a return address, as found in a backtrace.
+    ///
+    /// The address of the call instruction, just before the return address,
+    /// is looked up, so that the line of the call is found rather than the
+    /// following one.
+    pub fn resolve(&self, return_address: u64) -> Option<Symbol<'_>> {
+        let probe = return_address.checked_sub(self.bias)?.checked_sub(1)?;
+        let function = self
+            .symbols
+            .get(probe)
+            .map(|symbol| format!("{:#}", rustc_demangle::demangle(symbol.name())));
+        let location = self.context.find_location(probe).ok().flatten();
+        if function.is_none() && location.is_none() {
+            return None;
+        }
+        Some(Symbol {
+            function,
+            file: location.as_ref().and_then(|location| location.file),
+            line: location.as_ref().and_then(|location| location.line),
+        })
+    }
+
+    /// Rewrites the address of a `#index: 0xaddress` line, as written by the
+    /// runner and by `Backtrace`, into `function (file:line)`.
+    ///
+    /// Other lines, and addresses which could not be resolved, are kept as
+    /// they are.
+    pub fn symbolize_line(&self, line: &str) -> String {
+        match find_address(line) {
+            Some((start, end, address)) => match self.resolve(address) {
+                Some(symbol) => format!("{}{}{}", &line[..start], symbol, &line[end..]),
+                None => String::from(line),
+            },
+            None => String::from(line),
+        }
+    }
+}
+
+/// Finds the address of a `#index: 0xaddress` line, returning its byte range
+/// and value.
+fn find_address(line: &str) -> Option<(usize, usize, u64)> {
+    let hash = line.find('#')?;
+    let rest = &line[hash + 1..];
+    let digits = rest.find(|c: char| !c.is_ascii_digit())?;
+    if digits == 0 || !rest[digits..].starts_with(": 0x") {
+        return None;
+    }
+    let start = hash + 1 + digits + ": ".len();
+    let hex = &line[start + "0x".len()..];
+    let hex_len = hex.find(|c: char| !c.is_ascii_hexdigit()).unwrap_or(hex.len());
+    let address = u64::from_str_radix(&hex[..hex_len], 16).ok()?;
+    Some((start, start + "0x".len() + hex_len, address))
+}
@@ -0,0 +1,80 @@
+//! The lock guarding the state of the runner and of the built-in reporters
+//!
+//! With feature `spin_once`, it is `spin::Mutex`. With feature `racy`, it is
+//! an unsynchronized cell for targets without atomic compare-and-swap, which
+//! is only sound as long as tests run on a single thread without being
+//! interrupted by code reporting to the same lock.
+
+#[cfg(feature = "spin_once")]
+pub(crate) use spin::{Mutex, MutexGuard};
+
+#[cfg(feature = "racy")]
+pub(crate) use self::racy::{Mutex, MutexGuard};
+
+#[cfg(feature = "racy")]
+mod racy {
+    use core::cell::{Cell, UnsafeCell};
+    use core::ops::{Deref, DerefMut};
+
+    pub(crate) struct Mutex<T> {
+        locked: Cell<bool>,
+        value: UnsafeCell<T>,
+    }
+
+    // Feature `racy` is only meant for single-threaded targets.
+    unsafe impl<T: Send> Sync for Mutex<T> {}
+
+    impl<T> Mutex<T> {
+        pub(crate) const fn new(value: T) -> Self {
+            Self {
+                locked: Cell::new(false),
+                value: UnsafeCell::new(value),
+            }
+        }
+
+        /// Locks the cell
+        ///
+        /// # Panics
+        ///
+        /// This function will panic if the cell is already locked, as waiting
+        /// would never end on a single thread.
+        pub(crate) fn lock(&self) -> MutexGuard<'_, T> {
+            match self.try_lock() {
+                Some(guard) => guard,
+                None => panic!("lock is already held"),
+            }
+        }
+
+        pub(crate) fn try_lock(&self) -> Option<MutexGuard<'_, T>> {
+            if self.locked.replace(true) {
+                None
+            } else {
+                Some(MutexGuard { mutex: self })
+            }
+        }
+    }
+
+    pub(crate) struct MutexGuard<'a, T> {
+        mutex: &'a Mutex<T>,
+    }
+
+    impl<T> Deref for MutexGuard<'_, T> {
+        type Target = T;
+
+        fn deref(&self) -> &T {
+            unsafe { &*self.mutex.value.get() }
+        }
+    }
+
+    impl<T> DerefMut for MutexGuard<'_, T> {
+        fn deref_mut(&mut self) -> &mut T {
+            unsafe { &mut *self.mutex.value.get() }
+        }
+    }
+
+    impl<T> Drop for MutexGuard<'_, T> {
+        fn drop(&mut self) {
+            self.mutex.locked.set(false);
+        }
+    }
+}
@@ -7,14 +7,18 @@
 //! # Usage
 //!
 //! The basic usage of this crate is through an attribute
-//! [`micro_test_case`].
+//! [`micro_test_case`]. By default it registers the test function with
+//! `#[test_case]`, which requires nightly `custom_test_frameworks`. On stable
+//! Rust, turn off the default feature `custom_test_frameworks` and collect the
+//! tests with [`tests!`] instead.
 //!
-//! Users of this crate should set a _processor_ with trait [`Process`] to process the
-//! test metadata and result. If no implementations are given, the crate will panic on the
-//! firstly executed test function.
+//! Users of this crate should set a _reporter_ implementing trait [`Report`]
+//! to process the test metadata and failures. If no reporter is given, the
+//! crate will panic on the firstly executed test function.
 //!
-//! [`Process`]: trait.Process.html
+//! [`Report`]: ../report/trait.Report.html
 //! [`micro_test_case`]: attr.micro_test_case.html
+//! [`tests!`]: ../macro.tests.html
 //!
 //! ## Examples
 //!
@@ -22,39 +26,40 @@
 //! #![feature(custom_test_frameworks)]
 //! #![test_runner(test_runner)]
 //!
+//! use micro_test::backtrace::CallStack;
+//! use micro_test::panic::PanicInfo;
+//! use micro_test::report::Report;
 //! use micro_test::test::Metadata;
-//! use micro_test::test::Result as TestResult;
 //!
 //! fn add_by_one(num: usize) -> usize {
 //!     num + 1
 //! }
 //!
-//! fn print_metadata(metadata: Metadata) {
-//!     match metadata.feature {
-//!         Some(feature) => {
-//!             println!("testing {} ({}) ...", metadata.target, feature);
-//!         }
-//!         None => {
-//!             println!("testing {} ...", metadata.target);
+//! struct Console;
+//!
+//! impl Report for Console {
+//!     fn test_started(&self, metadata: &Metadata) {
+//!         match metadata.feature {
+//!             Some(feature) => {
+//!                 println!("testing {} ({}) ...", metadata.target, feature);
+//!             }
+//!             None => {
+//!                 println!("testing {} ...", metadata.target);
+//!             }
 //!         }
 //!     }
-//! }
 //!
-//! fn print_result(result: TestResult) {
-//!     match result {
-//!         Ok(_) => println!("ok"),
-//!         Err(e) => println!("FAILED: {}", e),
+//!     fn assertion_failed(&self, info: &PanicInfo) {
+//!         println!("FAILED: {}", info);
 //!     }
 //! }
 //!
-//! fn test_runner(tests: &[&dyn Fn()]) {
-//!     micro_test::report::set_reporter(&micro_test::report::Reporter {
-//!         metadata: Some(print_metadata),
-//!         result: Some(print_result),
-//!         call_stack: None,
-//!     });
+//! fn test_runner(tests: &[&dyn Fn() -> Result<(), CallStack>]) {
+//!     micro_test::report::set_reporter(&Console);
 //!     for test in tests {
-//!         test();
+//!         if test().is_ok() {
+//!             println!("ok");
+//!         }
 //!     }
 //! }
 //!
@@ -96,17 +101,22 @@
 //!
 //! # Comparison
 //!
-//! Different from `μtest`, this crate doesn't have `#[should_panic]` support,
-//! and doesn't intend to replace `panic!` macro invocations. Plus, this crate
-//! use trait to let users register their hook functions, instead of using
-//! `extern "Rust"`.
+//! Different from `μtest`, this crate only supports `#[should_panic]` through
+//! `#[micro_should_fail]`, which is satisfied by a `micro_panic!` or, with
+//! feature `std`, by a real panic, and doesn't intend to replace `panic!` macro
+//! invocations. Plus, this crate use trait to let users register their hook
+//! functions, instead of using `extern "Rust"`.
 //!
 //! Different from test framework provided by Rust, this crate doesn't support
-//! measuring, benchmarking, filtering, ignoring tests (yet) and mark test with
-//! `#[should_panic]`. Measuring and Ignoring tests is planned to be supported.
+//! measuring and benchmarking tests (yet). Tests could be filtered at runtime
+//! by the [`runner`](../runner/index.html), ignored with `#[micro_ignore]` or
+//! `#[micro_ignore = "reason"]`, and the equivalent of `#[should_panic]` is
+//! `#[micro_should_fail]` or `#[micro_should_fail(expected = "message")]`.
+//! Measuring tests is planned to be supported.
 //!
 
 pub use micro_test_macros::micro_test_case;
+use crate::backtrace::CallStack;
 use core::fmt::Result as FmtResult;
 use core::fmt::{Debug, Display, Formatter};
 
@@ -117,8 +127,7 @@ pub use crate::panic::PanicInfo as Error;
 /// # Use
 ///
 /// `Metadata` structs are created at the beginning of test function. They are
-/// send to [`prepare`] method of a user-defined variable with the trait
-/// [`Process`] to be processed.
+/// sent to the [`test_started`] method of the reporter set by users.
 ///
 /// # Examples
 ///
@@ -139,16 +148,35 @@ pub use crate::panic::PanicInfo as Error;
 /// print_metadata(Metadata {
 ///     target: "crate::print_metadata",
 ///     feature: Some("metadata printing"),
+///     ..Metadata::new()
 /// });
 /// # }
 /// ```
 ///
-/// [`prepare`]: trait.Process.html#tymethod.write_str
-/// [`Process`]: trait.Process.html
+/// [`test_started`]: ../report/trait.Report.html#method.test_started
 #[derive(Copy, Clone, Debug)]
 pub struct Metadata {
     pub target: &'static str,
     pub feature: Option<&'static str>,
+    /// The name of the test function.
+    pub name: &'static str,
+    /// The `file!()` where the test function is defined.
+    pub file: &'static str,
+    /// The `line!()` where the test function is defined.
+    pub line: u32,
+    /// The `module_path!()` of the test function.
+    pub module_path: &'static str,
+    /// Whether the test is marked with `#[micro_ignore]`. Ignored tests
+    /// report their metadata and return without running their body.
+    pub ignore: bool,
+    /// The reason given by `#[micro_ignore = "reason"]`, if any.
+    pub ignore_message: Option<&'static str>,
+    /// Whether the test is marked with `#[micro_should_fail]`. Such a test
+    /// passes only if a `micro_panic!` fires in it.
+    pub should_fail: bool,
+    /// The substring given by `#[micro_should_fail(expected = "message")]`,
+    /// which the panic message should contain.
+    pub expected: Option<&'static str>,
 }
 
 impl Display for Metadata {
@@ -160,30 +188,141 @@ impl Display for Metadata {
     }
 }
 
+impl Default for Metadata {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Metadata {
     pub const fn new() -> Self {
         Self {
             target: "",
             feature: None,
+            name: "",
+            file: "",
+            line: 0,
+            module_path: "",
+            ignore: false,
+            ignore_message: None,
+            should_fail: false,
+            expected: None,
         }
     }
 }
 
+/// A registered test function
+///
+/// On stable Rust, where `#[test_case]` is unavailable, tests are collected
+/// into a `&'static [TestDescriptor]` with the [`tests!`] macro and passed to
+/// the [`runner`].
+///
+/// [`tests!`]: ../macro.tests.html
+/// [`runner`]: ../runner/index.html
+#[derive(Copy, Clone, Debug)]
+pub struct TestDescriptor {
+    /// The path of the test function, as written in [`tests!`].
+    ///
+    /// [`tests!`]: ../macro.tests.html
+    pub name: &'static str,
+    pub run: fn() -> ::core::result::Result<(), CallStack>,
+}
+
+/// Collects test functions into a `&'static [TestDescriptor]`
+///
+/// This is the stable alternative to `#[test_case]`. Turn off the default
+/// feature `custom_test_frameworks` so that `#[micro_test_case]` doesn't emit
+/// `#[test_case]`, then list the test functions explicitly.
+///
+/// # Examples
+///
+/// ```rust,ignore
+/// use micro_test::test::TestDescriptor;
+///
+/// static TESTS: &[TestDescriptor] = micro_test::tests![
+///     tests::test_add_by_one,
+///     tests::test_add_by_two,
+/// ];
+///
+/// #[test]
+/// fn micro_tests() {
+///     let mut output = String::new();
+///     let summary = micro_test::runner::run(TESTS, &mut output);
+///     print!("{}", output);
+///     assert!(summary.is_ok());
+/// }
+/// ```
+#[macro_export]
+macro_rules! tests {
+    ($($test:path),* $(,)?) => {
+        &[$(
+            $crate::test::TestDescriptor {
+                name: ::core::stringify!($test),
+                run: $test,
+            }
+        ),*]
+    };
+}
+
+/// Passes the metadata of a starting test to the
+/// [`Report::test_started`](../report/trait.Report.html#method.test_started)
+/// method of the reporter.
+pub fn report_metadata(metadata: &Metadata) {
+    crate::report::reporter().test_started(metadata);
+}
+
 #[cfg(feature = "spin_once")]
-static METADATA_HANDLER: spin::Once<fn(&Metadata)> = spin::Once::new();
+static METADATA_FILTER: spin::Once<fn(&Metadata) -> bool> = spin::Once::new();
 
-pub fn set_metadata_reporter(reporter: fn(&Metadata)) {
-    if METADATA_HANDLER.is_completed() {
-        panic!("micro_test metadata reporter has already been initialized");
+/// Sets the global test filter
+///
+/// Before reporting its metadata, every test function asks the filter whether
+/// it should run. Tests rejected by the filter neither report their metadata
+/// nor run their body.
+///
+/// # Panics
+///
+/// This function will panic on its second call.
+#[cfg(feature = "spin_once")]
+pub fn set_metadata_filter(filter: fn(&Metadata) -> bool) {
+    if METADATA_FILTER.is_completed() {
+        panic!("micro_test metadata filter has already been initialized");
     } else {
-        METADATA_HANDLER.call_once(|| reporter);
+        METADATA_FILTER.call_once(|| filter);
     }
 }
 
-pub fn report_metadata(metadata: &Metadata) {
-    match METADATA_HANDLER.get() {
-        Some(metadata_handler) => metadata_handler(metadata),
-        None => panic!("metadata reporter has not been initialized"),
+/// Returns whether the test with `metadata` should run. All tests run if no
+/// filter has been set.
+#[cfg(feature = "spin_once")]
+pub fn filter_metadata(metadata: &Metadata) -> bool {
+    match METADATA_FILTER.get() {
+        Some(metadata_filter) => metadata_filter(metadata),
+        None => true,
+    }
+}
+
+#[cfg(feature = "racy")]
+static mut METADATA_FILTER_RACY: Option<fn(&Metadata) -> bool> = None;
+
+/// A thread-unsafe version of [`set_metadata_filter`]
+///
+/// # Safety
+///
+/// This function is only safe to call when no test is running and no other
+/// thread is reading the filter.
+///
+/// [`set_metadata_filter`]: fn.set_metadata_filter.html
+#[cfg(feature = "racy")]
+pub unsafe fn set_metadata_filter_racy(filter: fn(&Metadata) -> bool) {
+    METADATA_FILTER_RACY = Some(filter);
+}
+
+#[cfg(feature = "racy")]
+pub fn filter_metadata(metadata: &Metadata) -> bool {
+    match unsafe { METADATA_FILTER_RACY } {
+        Some(metadata_filter) => metadata_filter(metadata),
+        None => true,
     }
 }
 
@@ -252,13 +391,29 @@ macro_rules! micro_assert {
     ($test_expr:expr $(,)?) => {
         match $test_expr {
             true => {},
-            false => $crate::micro_panic!("assertion failed: `{}`", ::core::stringify!($test_expr)),
+            false => $crate::micro_panic!(@assertion &$crate::panic::AssertionFailure {
+                kind: $crate::panic::AssertionKind::Bool,
+                left_expr: ::core::stringify!($test_expr),
+                right_expr: None,
+                left: None,
+                right: None,
+                message: None,
+            }, "assertion failed: `{}`", ::core::stringify!($test_expr)),
         }
     };
     ($test_expr:expr, $($arg:tt)+) => {
         match $test_expr {
             true => {},
-            false => $crate::micro_panic!($($arg)+),
+            false => match format_args!($($arg)+) {
+                message => $crate::micro_panic!(@assertion &$crate::panic::AssertionFailure {
+                    kind: $crate::panic::AssertionKind::Bool,
+                    left_expr: ::core::stringify!($test_expr),
+                    right_expr: None,
+                    left: None,
+                    right: None,
+                    message: Some(&message),
+                }, "{}", message),
+            },
         }
     }
 }
@@ -272,7 +427,14 @@ macro_rules! micro_assert_eq {
         match (&$left, &$right) {
             (left_val, right_val) => {
                 if !(*left_val == *right_val) {
-                    $crate::micro_panic!(r#"assertion failed: `(left == right)`
+                    $crate::micro_panic!(@assertion &$crate::panic::AssertionFailure {
+                        kind: $crate::panic::AssertionKind::Eq,
+                        left_expr: ::core::stringify!($left),
+                        right_expr: Some(::core::stringify!($right)),
+                        left: Some(left_val),
+                        right: Some(right_val),
+                        message: None,
+                    }, r#"assertion failed: `(left == right)`
  left: `{:?}`,
 right: `{:?}`"#, left_val, right_val);
                 }
@@ -283,9 +445,18 @@ right: `{:?}`"#, left_val, right_val);
         match (&$left, &$right) {
             (left_val, right_val) => {
                 if !(*left_val == *right_val) {
-                    $crate::micro_panic!(r#"assertion failed: `(left == right)`
+                    match format_args!($($arg)+) {
+                        message => $crate::micro_panic!(@assertion &$crate::panic::AssertionFailure {
+                            kind: $crate::panic::AssertionKind::Eq,
+                            left_expr: ::core::stringify!($left),
+                            right_expr: Some(::core::stringify!($right)),
+                            left: Some(left_val),
+                            right: Some(right_val),
+                            message: Some(&message),
+                        }, r#"assertion failed: `(left == right)`
  left: `{:?}`,
-right: `{:?}`: {}"#, left_val, right_val, $($arg)+);
+right: `{:?}`: {}"#, left_val, right_val, message),
+                    }
                 }
             }
         }
@@ -301,7 +472,14 @@ macro_rules! micro_assert_ne {
         match (&$left, &$right) {
             (left_val, right_val) => {
                 if !(*left_val != *right_val) {
-                    $crate::micro_panic!(r#"assertion failed: `(left != right)`
+                    $crate::micro_panic!(@assertion &$crate::panic::AssertionFailure {
+                        kind: $crate::panic::AssertionKind::Ne,
+                        left_expr: ::core::stringify!($left),
+                        right_expr: Some(::core::stringify!($right)),
+                        left: Some(left_val),
+                        right: Some(right_val),
+                        message: None,
+                    }, r#"assertion failed: `(left != right)`
  left: `{:?}`,
 right: `{:?}`"#, left_val, right_val);
                 }
@@ -312,9 +490,191 @@ right: `{:?}`"#, left_val, right_val);
         match (&$left, &$right) {
             (left_val, right_val) => {
                 if !(*left_val != *right_val) {
-                    $crate::micro_panic!(relay r#"assertion failed: `(left != right)`
+                    match format_args!($($arg)+) {
+                        message => $crate::micro_panic!(@assertion &$crate::panic::AssertionFailure {
+                            kind: $crate::panic::AssertionKind::Ne,
+                            left_expr: ::core::stringify!($left),
+                            right_expr: Some(::core::stringify!($right)),
+                            left: Some(left_val),
+                            right: Some(right_val),
+                            message: Some(&message),
+                        }, r#"assertion failed: `(left != right)`
+ left: `{:?}`,
+right: `{:?}`: {}"#, left_val, right_val, message),
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The soft assertion macro used in micro_test
+///
+/// Unlike [`micro_assert`](macro.micro_assert.html), a failed expectation
+/// doesn't return from the test function. It is passed to the reporter
+/// and the test keeps running, then the test function marked with
+/// `#[micro_test_case]` fails when it finishes. This way, every mismatch is
+/// reported in a single run.
+///
+/// # Example
+///
+/// ```rust
+/// # #![feature(custom_test_frameworks)]
+/// # use micro_test::micro_expect;
+/// # use micro_test::test::micro_test_case;
+/// # #[micro_test_case]
+/// # fn test() {
+/// for reg in 0..4u32 {
+///     micro_expect!(reg < 4, "register {} out of range", reg);
+/// }
+/// # }
+/// ```
+#[macro_export]
+macro_rules! micro_expect {
+    ($test_expr:expr $(,)?) => {
+        match $test_expr {
+            true => {},
+            false => $crate::panic::handle_expectation_failure(&$crate::panic::PanicInfo {
+                message: Some(&format_args!("expectation failed: `{}`", ::core::stringify!($test_expr))),
+                location: ::core::panic::Location::caller(),
+                assertion: Some(&$crate::panic::AssertionFailure {
+                    kind: $crate::panic::AssertionKind::Bool,
+                    left_expr: ::core::stringify!($test_expr),
+                    right_expr: None,
+                    left: None,
+                    right: None,
+                    message: None,
+                }),
+                backtrace: None,
+            }),
+        }
+    };
+    ($test_expr:expr, $($arg:tt)+) => {
+        match $test_expr {
+            true => {},
+            false => match format_args!($($arg)+) {
+                message => $crate::panic::handle_expectation_failure(&$crate::panic::PanicInfo {
+                    message: Some(&format_args!("{}", message)),
+                    location: ::core::panic::Location::caller(),
+                    assertion: Some(&$crate::panic::AssertionFailure {
+                        kind: $crate::panic::AssertionKind::Bool,
+                        left_expr: ::core::stringify!($test_expr),
+                        right_expr: None,
+                        left: None,
+                        right: None,
+                        message: Some(&message),
+                    }),
+                    backtrace: None,
+                }),
+            },
+        }
+    }
+}
+
+/// Expects two expression is equal.
+///
+/// See [`micro_expect](macro.micro_expect.html).
+#[macro_export]
+macro_rules! micro_expect_eq {
+    ($left:expr, $right:expr $(,)?) => {
+        match (&$left, &$right) {
+            (left_val, right_val) => {
+                if !(*left_val == *right_val) {
+                    $crate::panic::handle_expectation_failure(&$crate::panic::PanicInfo {
+                        message: Some(&format_args!(r#"expectation failed: `(left == right)`
+ left: `{:?}`,
+right: `{:?}`"#, left_val, right_val)),
+                        location: ::core::panic::Location::caller(),
+                        assertion: Some(&$crate::panic::AssertionFailure {
+                            kind: $crate::panic::AssertionKind::Eq,
+                            left_expr: ::core::stringify!($left),
+                            right_expr: Some(::core::stringify!($right)),
+                            left: Some(left_val),
+                            right: Some(right_val),
+                            message: None,
+                        }),
+                        backtrace: None,
+                    });
+                }
+            }
+        }
+    };
+    ($left:expr, $right:expr, $($arg:tt)+) => {
+        match (&$left, &$right) {
+            (left_val, right_val) => {
+                if !(*left_val == *right_val) {
+                    match format_args!($($arg)+) {
+                        message => $crate::panic::handle_expectation_failure(&$crate::panic::PanicInfo {
+                            message: Some(&format_args!(r#"expectation failed: `(left == right)`
+ left: `{:?}`,
+right: `{:?}`: {}"#, left_val, right_val, message)),
+                            location: ::core::panic::Location::caller(),
+                            assertion: Some(&$crate::panic::AssertionFailure {
+                                kind: $crate::panic::AssertionKind::Eq,
+                                left_expr: ::core::stringify!($left),
+                                right_expr: Some(::core::stringify!($right)),
+                                left: Some(left_val),
+                                right: Some(right_val),
+                                message: Some(&message),
+                            }),
+                            backtrace: None,
+                        }),
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Expects two expression is not equal.
+///
+/// See [`micro_expect](macro.micro_expect.html).
+#[macro_export]
+macro_rules! micro_expect_ne {
+    ($left:expr, $right:expr $(,)?) => {
+        match (&$left, &$right) {
+            (left_val, right_val) => {
+                if !(*left_val != *right_val) {
+                    $crate::panic::handle_expectation_failure(&$crate::panic::PanicInfo {
+                        message: Some(&format_args!(r#"expectation failed: `(left != right)`
+ left: `{:?}`,
+right: `{:?}`"#, left_val, right_val)),
+                        location: ::core::panic::Location::caller(),
+                        assertion: Some(&$crate::panic::AssertionFailure {
+                            kind: $crate::panic::AssertionKind::Ne,
+                            left_expr: ::core::stringify!($left),
+                            right_expr: Some(::core::stringify!($right)),
+                            left: Some(left_val),
+                            right: Some(right_val),
+                            message: None,
+                        }),
+                        backtrace: None,
+                    });
+                }
+            }
+        }
+    };
+    ($left:expr, $right:expr, $($arg:tt)+) => {
+        match (&$left, &$right) {
+            (left_val, right_val) => {
+                if !(*left_val != *right_val) {
+                    match format_args!($($arg)+) {
+                        message => $crate::panic::handle_expectation_failure(&$crate::panic::PanicInfo {
+                            message: Some(&format_args!(r#"expectation failed: `(left != right)`
  left: `{:?}`,
-right: `{:?}`: {}"#, left_val, right_val, $($arg)+);
+right: `{:?}`: {}"#, left_val, right_val, message)),
+                            location: ::core::panic::Location::caller(),
+                            assertion: Some(&$crate::panic::AssertionFailure {
+                                kind: $crate::panic::AssertionKind::Ne,
+                                left_expr: ::core::stringify!($left),
+                                right_expr: Some(::core::stringify!($right)),
+                                left: Some(left_val),
+                                right: Some(right_val),
+                                message: Some(&message),
+                            }),
+                            backtrace: None,
+                        }),
+                    }
                 }
             }
         }
@@ -0,0 +1,91 @@
+#![feature(custom_test_frameworks)]
+#![test_runner(test_runner)]
+
+use std::sync::Mutex;
+
+use micro_test::backtrace::CallStack;
+use micro_test::panic::{AssertionKind, PanicInfo};
+use micro_test::report::{set_reporter, Report};
+
+#[derive(Debug, PartialEq)]
+struct Failure {
+    kind: AssertionKind,
+    left_expr: String,
+    right_expr: Option<String>,
+    left: Option<String>,
+    right: Option<String>,
+    message: Option<String>,
+}
+
+static FAILURES: Mutex<Vec<Failure>> = Mutex::new(Vec::new());
+
+struct CollectFailures;
+
+impl Report for CollectFailures {
+    fn assertion_failed(&self, info: &PanicInfo) {
+        let assertion = info.assertion.unwrap();
+        FAILURES.lock().unwrap().push(Failure {
+            kind: assertion.kind,
+            left_expr: assertion.left_expr.to_string(),
+            right_expr: assertion.right_expr.map(str::to_string),
+            left: assertion.left.map(|left| format!("{:?}", left)),
+            right: assertion.right.map(|right| format!("{:?}", right)),
+            message: assertion.message.map(|message| message.to_string()),
+        });
+    }
+}
+
+fn test_runner(tests: &[&dyn Fn() -> Result<(), CallStack>]) {
+    set_reporter(&CollectFailures);
+    for test in tests {
+        assert!(test().is_err());
+    }
+    let mut failures = FAILURES.lock().unwrap();
+    failures.sort_by_key(|failure| format!("{:?}", failure.kind));
+    assert_eq!(*failures, vec![
+        Failure {
+            kind: AssertionKind::Bool,
+            left_expr: "1 + 1 == 3".to_string(),
+            right_expr: None,
+            left: None,
+            right: None,
+            message: Some("math is broken: 2".to_string()),
+        },
+        Failure {
+            kind: AssertionKind::Eq,
+            left_expr: "vec![1, 2]".to_string(),
+            right_expr: Some("[1, 3]".to_string()),
+            left: Some("[1, 2]".to_string()),
+            right: Some("[1, 3]".to_string()),
+            message: None,
+        },
+        Failure {
+            kind: AssertionKind::Ne,
+            left_expr: "\"reg\"".to_string(),
+            right_expr: Some("\"reg\"".to_string()),
+            left: Some("\"reg\"".to_string()),
+            right: Some("\"reg\"".to_string()),
+            message: Some("register 4".to_string()),
+        },
+    ]);
+}
+
+mod tests {
+    use micro_test::{micro_assert, micro_assert_eq, micro_expect_ne};
+    use micro_test::test::micro_test_case;
+
+    #[micro_test_case]
+    pub fn test_bool() {
+        micro_assert!(1 + 1 == 3, "math is broken: {}", 1 + 1);
+    }
+
+    #[micro_test_case]
+    pub fn test_eq() {
+        micro_assert_eq!(vec![1, 2], [1, 3]);
+    }
+
+    #[micro_test_case]
+    pub fn test_ne() {
+        micro_expect_ne!("reg", "reg", "register {}", 4);
+    }
+}
@@ -0,0 +1,221 @@
+use micro_test::backtrace::{CallStack, FuncArgs, FuncCall, FUNC_ARGS_CAPACITY};
+use micro_test::micro_call;
+use micro_test::micro_panic;
+use micro_test::panic::{micro_panic_relay, PanicMessage};
+use micro_test::report::{set_reporter, Report};
+
+static REPORTER: std::sync::Once = std::sync::Once::new();
+
+struct Quiet;
+
+impl Report for Quiet {}
+
+fn func_call(name: &'static str) -> FuncCall {
+    FuncCall { name, file: "tests/call_stack.rs", line: 1, column: 1, receiver: None, args: None }
+}
+
+#[micro_panic_relay]
+fn check_len(v: &[u8], len: usize) {
+    if v.len() != len {
+        micro_panic!("unexpected length");
+    }
+}
+
+#[micro_panic_relay]
+fn check_twice(v: &[u8], len: usize) {
+    micro_call!(relay check_len(v, len));
+    micro_call!(relay_debug check_len(v, len + 1));
+}
+
+#[test]
+fn call_stack_truncates_after_capacity() {
+    let mut call_stack = CallStack::<2>::new();
+    for name in ["inner", "middle", "outer"] {
+        call_stack.push(func_call(name));
+    }
+    assert_eq!(call_stack.len(), 2);
+    assert!(call_stack.is_truncated());
+    assert_eq!(call_stack.calls()[1].name, "middle");
+    assert_eq!(
+        call_stack.to_string(),
+        concat!(
+            "#0: inner at tests/call_stack.rs:1:1\n",
+            "#1: middle at tests/call_stack.rs:1:1\n",
+            "... truncated after 2 frames\n",
+        )
+    );
+}
+
+#[test]
+fn call_stack_records_call_sites_and_args() {
+    REPORTER.call_once(|| set_reporter(&Quiet));
+    let call_stack = micro_call!(result check_twice(&[1, 2], 1)).unwrap_err();
+    assert_eq!(call_stack.len(), 1);
+    assert_eq!(call_stack.calls()[0].line, 26);
+
+    let call_stack = micro_call!(result check_twice(&[1, 2], 2)).unwrap_err();
+    let call = call_stack.calls()[0];
+    assert_eq!(call.file, "tests/call_stack.rs");
+    assert_eq!(call.line, 27);
+    assert_eq!(call.args.unwrap().as_str(), "[1, 2], 3");
+    assert_eq!(call.to_string(), "check_len([1, 2], 3) at tests/call_stack.rs:27:5");
+}
+
+#[test]
+fn func_args_truncate_at_capacity() {
+    let long = "é".repeat(FUNC_ARGS_CAPACITY);
+    let args = FuncArgs::from_debug(&[&1, &long]);
+    assert!(args.is_truncated());
+    assert!(args.as_str().starts_with("1, \"éé"));
+    assert!(args.as_str().len() <= FUNC_ARGS_CAPACITY);
+    assert!(args.to_string().ends_with("..."));
+}
+
+mod driver {
+    use micro_test::micro_call;
+    use micro_test::micro_panic;
+    use micro_test::panic::micro_panic_relay;
+
+    pub struct Uart {
+        pub ready: bool,
+        pub callback: fn(u8) -> Result<u8, micro_test::backtrace::CallStack>,
+    }
+
+    impl Uart {
+        #[micro_panic_relay]
+        pub fn read_reg(&self, reg: u8) -> u8 {
+            if self.ready {
+                reg
+            } else {
+                micro_panic!("not ready")
+            }
+        }
+
+        #[micro_panic_relay]
+        pub fn reset() {
+            micro_panic!("reset failed");
+        }
+
+        #[micro_panic_relay]
+        pub fn read(&mut self) -> u8 {
+            micro_call!(relay self.read_reg(0))
+        }
+
+        #[micro_panic_relay]
+        pub fn reset_self() {
+            micro_call!(relay Self::reset());
+        }
+
+        #[micro_panic_relay]
+        pub fn notify(&self) {
+            micro_call!(relay (self.callback)(1));
+        }
+    }
+
+    #[allow(unreachable_code)]
+    #[micro_panic_relay]
+    pub fn init<T: Default>() -> T {
+        micro_panic!("init failed")
+    }
+}
+
+#[micro_panic_relay]
+fn call_init() -> u8 {
+    micro_call!(relay driver::init::<u8>())
+}
+
+#[micro_panic_relay]
+fn call_closure(x: u8) -> u8 {
+    #[allow(clippy::result_large_err)]
+    let check = |x: u8| -> Result<u8, CallStack> {
+        driver::init::<u8>()?;
+        Ok(x)
+    };
+    micro_call!(relay_debug check(x))
+}
+
+#[micro_panic_relay]
+fn check_with_question_mark(v: &[u8]) -> usize {
+    check_len(v, 2)?;
+    let len = v.len();
+    let checked = check_twice(v, len);
+    checked?;
+    len
+}
+
+#[test]
+fn call_stack_names_methods_paths_and_closures() {
+    REPORTER.call_once(|| set_reporter(&Quiet));
+    let mut uart = driver::Uart { ready: false, callback: call_closure };
+    assert_eq!(micro_call!(result uart.read()).unwrap_err().calls()[0].to_string(), "Uart::read_reg at tests/call_stack.rs:101:13");
+    assert_eq!(micro_call!(result driver::Uart::reset_self()).unwrap_err().calls()[0].to_string(), "Uart::reset at tests/call_stack.rs:106:13");
+    assert_eq!(micro_call!(result call_init()).unwrap_err().calls()[0].name, "driver::init");
+
+    let call_stack = micro_call!(result uart.notify()).unwrap_err();
+    let names: Vec<String> = call_stack.calls().iter().map(|call| call.to_string()).collect();
+    assert_eq!(names, ["check(1) at tests/call_stack.rs:134:5", "self.callback at tests/call_stack.rs:111:13"]);
+
+    uart.ready = true;
+    assert_eq!(micro_call!(unwrap uart.read()), 0);
+}
+
+#[test]
+fn question_mark_relays_call_stack() {
+    REPORTER.call_once(|| set_reporter(&Quiet));
+    let call_stack = micro_call!(result check_with_question_mark(&[1])).unwrap_err();
+    assert_eq!(call_stack.calls()[0].to_string(), "check_len at tests/call_stack.rs:139:20");
+
+    let call_stack = micro_call!(result check_with_question_mark(&[1, 2])).unwrap_err();
+    let names: Vec<String> = call_stack.calls().iter().map(|call| call.to_string()).collect();
+    assert_eq!(names, ["check_len([1, 2], 3) at tests/call_stack.rs:27:5", "checked at tests/call_stack.rs:142:12"]);
+}
+
+#[test]
+fn call_stack_holds_panic_message_and_location() {
+    REPORTER.call_once(|| set_reporter(&Quiet));
+    let call_stack = micro_call!(result check_with_question_mark(&[1])).unwrap_err();
+    assert_eq!(call_stack.message().unwrap().as_str(), "unexpected length");
+    let location = call_stack.location().unwrap();
+    assert_eq!((location.file(), location.line()), ("tests/call_stack.rs", 20));
+
+    let call_stack = micro_call!(result driver::Uart::reset_self()).unwrap_err();
+    assert_eq!(call_stack.message().unwrap().to_string(), "reset failed");
+    assert!(CallStack::<1>::new().message().is_none());
+}
+
+#[test]
+fn panic_message_truncates_at_capacity() {
+    let message = PanicMessage::<8>::from_args(&format_args!("{}", "aéééé"));
+    assert!(message.is_truncated());
+    assert_eq!(message.as_str(), "aééé");
+    assert_eq!(message.to_string(), "aééé...");
+}
+
+struct Buffer(Vec<u8>);
+
+impl Buffer {
+    #[micro_panic_relay]
+    fn first(self) -> u8 {
+        match self.0.first() {
+            Some(&first) => first,
+            None => micro_panic!("empty buffer"),
+        }
+    }
+}
+
+fn make_buffer(v: Vec<u8>) -> Buffer {
+    Buffer(v)
+}
+
+#[micro_panic_relay]
+fn first_of(v: Vec<u8>) -> u8 {
+    micro_call!(relay make_buffer(v).first())
+}
+
+#[test]
+fn call_stack_names_receivers_moving_values() {
+    REPORTER.call_once(|| set_reporter(&Quiet));
+    assert_eq!(micro_call!(unwrap first_of(vec![7])), 7);
+    let call_stack = micro_call!(result first_of(Vec::new())).unwrap_err();
+    assert_eq!(call_stack.calls()[0].to_string(), "Buffer::first at tests/call_stack.rs:212:5");
+}
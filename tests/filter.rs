@@ -0,0 +1,47 @@
+#![feature(custom_test_frameworks)]
+#![test_runner(test_runner)]
+
+use micro_test::backtrace::CallStack;
+use micro_test::runner::{self, Filter, Summary};
+
+fn add_by_one(num: usize) -> usize {
+    num + 1
+}
+
+fn test_runner(tests: &[&dyn Fn() -> Result<(), CallStack>]) {
+    let mut output = String::new();
+    let summary = runner::run_filtered(tests, Filter::new("add_by_*"), &mut output);
+    print!("{}", output);
+    assert_eq!(summary, Summary { passed: 2, failed: 0, ignored: 0, filtered_out: 2 });
+    assert!(output.starts_with("\nrunning 2 tests\n"));
+    assert!(output.contains("test add_by_one ... ok\n"));
+    assert!(output.contains("test add_by_two (overflow) ... ok\n"));
+    assert!(!output.contains("sub_by_one"));
+    assert!(output.ends_with("\ntest result: ok. 2 passed; 0 failed; 0 ignored; 2 filtered out\n"));
+}
+
+mod tests {
+    use super::*;
+    use micro_test::micro_assert_eq;
+    use micro_test::test::micro_test_case;
+
+    #[micro_test_case(target = "add_by_one")]
+    pub fn test_add_by_one() {
+        micro_assert_eq!(add_by_one(1), 2);
+    }
+
+    #[micro_test_case(target = "add_by_two", feature = "overflow")]
+    pub fn test_add_by_two() {
+        micro_assert_eq!(add_by_one(add_by_one(1)), 3);
+    }
+
+    #[micro_test_case(target = "sub_by_one")]
+    pub fn test_sub_by_one() {
+        micro_assert_eq!(add_by_one(1), 1);
+    }
+
+    #[micro_test_case(target = "sub_by_one", feature = "inverse of add_by_one")]
+    pub fn test_sub_by_one_add_by_one() {
+        micro_assert_eq!(add_by_one(1), 1);
+    }
+}
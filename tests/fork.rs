@@ -0,0 +1,62 @@
+#![cfg(all(feature = "std", target_os = "linux"))]
+#![feature(custom_test_frameworks)]
+#![test_runner(test_runner)]
+
+use micro_test::backtrace::CallStack;
+use micro_test::runner::{self, Filter, Summary};
+
+fn test_runner(tests: &[&dyn Fn() -> Result<(), CallStack>]) {
+    let mut output = String::new();
+    let summary = runner::run_forked(tests, Filter::Substring(""), &mut output);
+    print!("{}", output);
+    assert_eq!(summary, Summary { passed: 2, failed: 5, ignored: 1, filtered_out: 0 });
+    assert!(output.contains("test forked (pass) ... ok\n"));
+    assert!(output.contains("test forked (assertion) ... FAILED\n    assertion failed: `1 + 1 == 3`"));
+    assert!(output.contains("test forked (panic) ... FAILED\n    forked panic: tests/fork.rs:"));
+    assert!(output.contains("test forked (abort) ... FAILED\n    note: test terminated by signal 6 (SIGABRT)\n"));
+    assert!(output.contains("test forked (segfault) ... FAILED\n    note: test terminated by signal 11 (SIGSEGV)\n"));
+    assert!(output.contains("test forked (exit) ... FAILED\n    note: test exited with code 3\n"));
+    assert!(output.contains("test forked (ignored) ... ignored\n"));
+    assert!(output.contains("test forked (after crashes) ... ok\n"));
+}
+
+mod tests {
+    use micro_test::micro_assert;
+    use micro_test::test::micro_test_case;
+
+    #[micro_test_case(target = "forked", feature = "pass")]
+    pub fn test_pass() {}
+
+    #[micro_test_case(target = "forked", feature = "assertion")]
+    pub fn test_assertion() {
+        micro_assert!(1 + 1 == 3);
+    }
+
+    #[micro_test_case(target = "forked", feature = "panic")]
+    pub fn test_panic() {
+        panic!("forked panic");
+    }
+
+    #[micro_test_case(target = "forked", feature = "abort")]
+    pub fn test_abort() {
+        std::process::abort();
+    }
+
+    #[micro_test_case(target = "forked", feature = "segfault")]
+    pub fn test_segfault() {
+        // An unmapped page, so that the fault is real rather than raised.
+        unsafe { core::ptr::write_volatile(core::ptr::dangling_mut::<u64>(), 0) };
+    }
+
+    #[micro_test_case(target = "forked", feature = "exit")]
+    pub fn test_exit() {
+        std::process::exit(3);
+    }
+
+    #[micro_test_case(target = "forked", feature = "ignored")]
+    #[micro_ignore]
+    pub fn test_ignored() {}
+
+    #[micro_test_case(target = "forked", feature = "after crashes")]
+    pub fn test_after_crashes() {}
+}
@@ -0,0 +1,84 @@
+#![cfg(all(feature = "std", target_os = "linux"))]
+#![feature(custom_test_frameworks)]
+#![test_runner(test_runner)]
+
+use micro_test::backtrace::CallStack;
+use micro_test::report::Libtest;
+use micro_test::runner::{self, Filter, Summary};
+
+static LIBTEST: Libtest<String> = Libtest::new(String::new());
+
+fn test_runner(tests: &[&dyn Fn() -> Result<(), CallStack>]) {
+    let summary = runner::run_forked_with_reporter(tests, Filter::Substring("forked"), &LIBTEST);
+    assert_eq!(summary, Summary { passed: 1, failed: 3, ignored: 0, filtered_out: 1 });
+    LIBTEST.with_sink(|output| {
+        assert_eq!(
+            output,
+            concat!(
+                "\n",
+                "running 4 tests\n",
+                "test tests::test_a_relay ... FAILED\n",
+                "test tests::test_b_assert_eq ... FAILED\n",
+                "test tests::test_c_should_fail - should panic ... ok\n",
+                "test tests::test_d_abort ... FAILED\n",
+                "\n",
+                "failures:\n",
+                "\n",
+                "---- tests::test_a_relay stdout ----\n",
+                "not ready: tests/fork_reporter.rs:53:9\n",
+                "#0: super::check(false) at tests/fork_reporter.rs:63:9\n",
+                "\n",
+                "---- tests::test_b_assert_eq stdout ----\n",
+                "assertion failed: `(left == right)`\n left: `2`,\nright: `3`: tests/fork_reporter.rs:68:9\n",
+                "\n",
+                "---- tests::test_d_abort stdout ----\n",
+                "note: test terminated by signal 6 (SIGABRT)\n",
+                "\n",
+                "\n",
+                "failures:\n",
+                "    tests::test_a_relay\n",
+                "    tests::test_b_assert_eq\n",
+                "    tests::test_d_abort\n",
+                "\n",
+                "test result: FAILED. 1 passed; 3 failed; 0 ignored; 0 measured; 1 filtered out; finished in 0.00s\n",
+                "\n",
+            )
+        );
+    });
+}
+
+#[micro_test::panic::micro_panic_relay]
+fn check(ready: bool) {
+    if !ready {
+        micro_test::micro_panic!("not ready");
+    }
+}
+
+mod tests {
+    use micro_test::{micro_assert_eq, micro_call, micro_panic};
+    use micro_test::test::micro_test_case;
+
+    #[micro_test_case(target = "forked")]
+    pub fn test_a_relay() {
+        micro_call!(relay_debug super::check(false));
+    }
+
+    #[micro_test_case(target = "forked")]
+    pub fn test_b_assert_eq() {
+        micro_assert_eq!(1 + 1, 3);
+    }
+
+    #[micro_test_case(target = "forked")]
+    #[micro_should_fail(expected = "overflow")]
+    pub fn test_c_should_fail() {
+        micro_panic!("overflow");
+    }
+
+    #[micro_test_case(target = "forked")]
+    pub fn test_d_abort() {
+        std::process::abort();
+    }
+
+    #[micro_test_case(target = "other")]
+    pub fn test_e_filtered_out() {}
+}
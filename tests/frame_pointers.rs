@@ -0,0 +1,64 @@
+//! Run with `RUSTFLAGS="-C force-frame-pointers=yes" cargo test --features frame_pointers`.
+#![cfg(all(feature = "frame_pointers", target_arch = "x86_64"))]
+
+use std::sync::Mutex;
+
+use micro_test::backtrace::Backtrace;
+use micro_test::micro_call;
+use micro_test::micro_panic;
+use micro_test::panic::{micro_panic_relay, PanicInfo};
+use micro_test::report::{set_reporter, Report};
+
+static BACKTRACE: Mutex<Option<Backtrace>> = Mutex::new(None);
+
+struct StoreBacktrace;
+
+impl Report for StoreBacktrace {
+    fn assertion_failed(&self, info: &PanicInfo) {
+        *BACKTRACE.lock().unwrap() = info.backtrace.copied();
+    }
+}
+
+/// Checks that `ip` is a return address into `function`, assuming that no
+/// test function here is longer than 4 KiB.
+fn returns_into(ip: usize, function: usize) -> bool {
+    ip > function && ip - function < 0x1000
+}
+
+#[inline(never)]
+fn inner() -> Backtrace<4> {
+    unsafe { Backtrace::capture(0) }
+}
+
+#[inline(never)]
+fn middle() -> Backtrace<4> {
+    inner()
+}
+
+#[inline(never)]
+fn outer() -> Backtrace<4> {
+    middle()
+}
+
+#[inline(never)]
+#[micro_panic_relay]
+fn panicking() {
+    micro_panic!("PANIC!");
+}
+
+#[test]
+fn capture_walks_frame_pointers() {
+    let backtrace = outer();
+    assert!(backtrace.len() >= 3);
+    assert!(returns_into(backtrace.frames()[0].ip(), inner as *const () as usize));
+    assert!(returns_into(backtrace.frames()[1].ip(), middle as *const () as usize));
+    assert!(returns_into(backtrace.frames()[2].ip(), outer as *const () as usize));
+}
+
+#[test]
+fn micro_panic_captures_backtrace() {
+    set_reporter(&StoreBacktrace);
+    assert!(micro_call!(result panicking()).is_err());
+    let backtrace = BACKTRACE.lock().unwrap().unwrap();
+    assert!(returns_into(backtrace.frames()[0].ip(), panicking as *const () as usize));
+}
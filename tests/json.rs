@@ -0,0 +1,51 @@
+#![feature(custom_test_frameworks)]
+#![test_runner(test_runner)]
+
+use micro_test::backtrace::CallStack;
+use micro_test::report::Json;
+use micro_test::runner::{self, Filter, Summary};
+
+static JSON: Json<String> = Json::new(String::new());
+
+fn test_runner(tests: &[&dyn Fn() -> Result<(), CallStack>]) {
+    let summary = runner::run_with_reporter(tests, Filter::Exact("json"), &JSON);
+    assert_eq!(summary, Summary { passed: 0, failed: 1, ignored: 2, filtered_out: 1 });
+    JSON.with_sink(|output| {
+        assert_eq!(
+            output,
+            concat!(
+                r#"{ "type": "suite", "event": "started", "test_count": 3 }"#, "\n",
+                r#"{ "type": "test", "event": "started", "name": "tests::test_a_control_chars" }"#, "\n",
+                r#"{ "type": "test", "name": "tests::test_a_control_chars", "event": "failed", "stdout": "\u0000\u0007 \"quoted\" C:\\uart\r\n\u001b[31m: tests/json.rs:38:9\n" }"#, "\n",
+                r#"{ "type": "test", "event": "started", "name": "tests::test_b_ignored" }"#, "\n",
+                r#"{ "type": "test", "name": "tests::test_b_ignored", "event": "ignored", "message": "needs \"hardware\"" }"#, "\n",
+                r#"{ "type": "test", "event": "started", "name": "tests::test_c_ignored_without_message" }"#, "\n",
+                r#"{ "type": "test", "name": "tests::test_c_ignored_without_message", "event": "ignored" }"#, "\n",
+                r#"{ "type": "suite", "event": "failed", "passed": 0, "failed": 1, "ignored": 2, "measured": 0, "filtered_out": 1, "exec_time": 0.000 }"#, "\n",
+            )
+        );
+    });
+}
+
+mod tests {
+    use micro_test::micro_panic;
+    use micro_test::test::micro_test_case;
+
+    // Control characters are escaped as `\u00XX`, except the ones with a
+    // short escape in both JSON and Rust.
+    #[micro_test_case(target = "json")]
+    pub fn test_a_control_chars() {
+        micro_panic!("\0\x07 \"quoted\" C:\\uart\r\n\x1b[31m");
+    }
+
+    #[micro_test_case(target = "json")]
+    #[micro_ignore = "needs \"hardware\""]
+    pub fn test_b_ignored() {}
+
+    #[micro_test_case(target = "json")]
+    #[micro_ignore]
+    pub fn test_c_ignored_without_message() {}
+
+    #[micro_test_case(target = "uart")]
+    pub fn test_d_filtered_out() {}
+}
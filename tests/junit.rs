@@ -0,0 +1,90 @@
+#![cfg(feature = "std")]
+#![feature(custom_test_frameworks)]
+#![test_runner(test_runner)]
+
+use std::time::Duration;
+
+use micro_test::backtrace::CallStack;
+use micro_test::bench::{self, Time};
+use micro_test::report::{JUnit, Report};
+use micro_test::runner::{self, Filter, Summary};
+
+static JUNIT: JUnit = JUnit::new(concat!(env!("CARGO_TARGET_TMPDIR"), "/junit.xml"));
+static UNWRITABLE: JUnit = JUnit::new(concat!(env!("CARGO_TARGET_TMPDIR"), "/missing/junit.xml"));
+
+/// Advances by 1.5 ms on every call, so that durations are predictable.
+struct StepTimer {
+    calls: std::sync::atomic::AtomicU64,
+}
+
+impl Time for StepTimer {
+    fn now(&self) -> Duration {
+        let calls = self.calls.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        Duration::from_micros(1500 * calls)
+    }
+}
+
+static TIMER: StepTimer = StepTimer { calls: std::sync::atomic::AtomicU64::new(0) };
+
+fn test_runner(tests: &[&dyn Fn() -> Result<(), CallStack>]) {
+    bench::set_timer(&TIMER);
+    let summary = runner::run_with_reporter(tests, Filter::Substring(""), &JUNIT);
+    assert_eq!(summary, Summary { passed: 1, failed: 2, ignored: 1, filtered_out: 0 });
+    assert!(JUNIT.take_error().is_none());
+    UNWRITABLE.run_finished(&summary);
+    assert!(UNWRITABLE.take_error().is_some());
+    let xml = std::fs::read_to_string(JUNIT.path()).unwrap();
+    assert_eq!(
+        xml,
+        concat!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+            "<testsuites>\n",
+            "  <testsuite name=\"junit\" tests=\"4\" failures=\"2\" errors=\"0\" skipped=\"1\" time=\"0.013\">\n",
+            "    <testcase classname=\"uart\" name=\"loopback\" time=\"0.002\"/>\n",
+            "    <testcase classname=\"uart\" name=\"test_b_assert\" time=\"0.002\">\n",
+            "      <failure message=\"1 &lt; 2 &amp; &quot;3&quot;&#10;&#9;\u{fffd}\">",
+            "1 &lt; 2 &amp; &quot;3&quot;\n\t\u{fffd}: tests/junit.rs:79:9\n",
+            "</failure>\n",
+            "    </testcase>\n",
+            "    <testcase classname=\"flash\" name=\"erase\">\n",
+            "      <skipped message=\"needs hardware\"/>\n",
+            "    </testcase>\n",
+            "    <testcase classname=\"uart\" name=\"relay\" time=\"0.002\">\n",
+            "      <failure message=\"not ready\">not ready: tests/junit.rs:66:9\n",
+            "#0: super::check at tests/junit.rs:88:9\n",
+            "</failure>\n",
+            "    </testcase>\n",
+            "  </testsuite>\n",
+            "</testsuites>\n",
+        )
+    );
+}
+
+#[micro_test::panic::micro_panic_relay]
+fn check(ready: bool) {
+    if !ready {
+        micro_test::micro_panic!("not ready");
+    }
+}
+
+mod tests {
+    use micro_test::{micro_assert, micro_call};
+    use micro_test::test::micro_test_case;
+
+    #[micro_test_case(target = "uart", feature = "loopback")]
+    pub fn test_a_pass() {}
+
+    #[micro_test_case(target = "uart")]
+    pub fn test_b_assert() {
+        micro_assert!(false, "1 < 2 & \"{}\"\n\t\u{1b}", 3);
+    }
+
+    #[micro_test_case(target = "flash", feature = "erase")]
+    #[micro_ignore = "needs hardware"]
+    pub fn test_c_ignored() {}
+
+    #[micro_test_case(target = "uart", feature = "relay")]
+    pub fn test_d_relay() {
+        micro_call!(relay super::check(false));
+    }
+}
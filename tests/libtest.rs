@@ -0,0 +1,88 @@
+#![feature(custom_test_frameworks)]
+#![test_runner(test_runner)]
+
+use micro_test::backtrace::CallStack;
+use micro_test::report::Libtest;
+use micro_test::runner::{self, Filter, Summary};
+
+static LIBTEST: Libtest<String> = Libtest::new(String::new());
+
+fn test_runner(tests: &[&dyn Fn() -> Result<(), CallStack>]) {
+    let summary = runner::run_with_reporter(tests, Filter::Substring(""), &LIBTEST);
+    assert_eq!(summary, Summary { passed: 2, failed: 3, ignored: 1, filtered_out: 0 });
+    LIBTEST.with_sink(|output| {
+        assert_eq!(
+            output,
+            concat!(
+                "\n",
+                "running 6 tests\n",
+                "test tests::test_a_pass ... ok\n",
+                "test tests::test_b_assert ... FAILED\n",
+                "test tests::test_c_ignored ... ignored, needs hardware\n",
+                "test tests::test_d_relay ... FAILED\n",
+                "test tests::test_e_should_fail - should panic ... ok\n",
+                "test tests::test_f_should_fail_passes - should panic ... FAILED\n",
+                "\n",
+                "failures:\n",
+                "\n",
+                "---- tests::test_b_assert stdout ----\n",
+                "assertion failed: `1 + 1 == 3`: tests/libtest.rs:67:9\n",
+                "\n",
+                "---- tests::test_d_relay stdout ----\n",
+                "not ready: tests/libtest.rs:54:9\n",
+                "#0: super::check at tests/libtest.rs:76:9\n",
+                "\n",
+                "---- tests::test_f_should_fail_passes stdout ----\n",
+                "note: test did not fail as expected\n",
+                "\n",
+                "\n",
+                "failures:\n",
+                "    tests::test_b_assert\n",
+                "    tests::test_d_relay\n",
+                "    tests::test_f_should_fail_passes\n",
+                "\n",
+                "test result: FAILED. 2 passed; 3 failed; 1 ignored; 0 measured; 0 filtered out; finished in 0.00s\n",
+                "\n",
+            )
+        );
+    });
+}
+
+#[micro_test::panic::micro_panic_relay]
+fn check(ready: bool) {
+    if !ready {
+        micro_test::micro_panic!("not ready");
+    }
+}
+
+mod tests {
+    use micro_test::{micro_assert, micro_call};
+    use micro_test::test::micro_test_case;
+
+    #[micro_test_case]
+    pub fn test_a_pass() {}
+
+    #[micro_test_case]
+    pub fn test_b_assert() {
+        micro_assert!(1 + 1 == 3);
+    }
+
+    #[micro_test_case]
+    #[micro_ignore = "needs hardware"]
+    pub fn test_c_ignored() {}
+
+    #[micro_test_case]
+    pub fn test_d_relay() {
+        micro_call!(relay super::check(false));
+    }
+
+    #[micro_test_case]
+    #[micro_should_fail]
+    pub fn test_e_should_fail() {
+        micro_assert!(false);
+    }
+
+    #[micro_test_case]
+    #[micro_should_fail]
+    pub fn test_f_should_fail_passes() {}
+}
@@ -0,0 +1,52 @@
+#![feature(custom_test_frameworks)]
+#![test_runner(test_runner)]
+
+use std::sync::Mutex;
+
+use micro_test::backtrace::CallStack;
+use micro_test::report::{set_reporter, Report};
+use micro_test::test::Metadata;
+
+static METADATA: Mutex<Vec<Metadata>> = Mutex::new(Vec::new());
+
+struct CollectMetadata;
+
+impl Report for CollectMetadata {
+    fn test_started(&self, metadata: &Metadata) {
+        METADATA.lock().unwrap().push(*metadata);
+    }
+}
+
+fn test_runner(tests: &[&dyn Fn() -> Result<(), CallStack>]) {
+    set_reporter(&CollectMetadata);
+    for test in tests {
+        test().unwrap();
+    }
+    let mut metadata = METADATA.lock().unwrap();
+    metadata.sort_by_key(|metadata| metadata.name);
+    assert_eq!(metadata.len(), 3);
+
+    assert_eq!(metadata[2].name, "test_with_path");
+    assert_eq!(metadata[2].target, "metadata::tests::with_path");
+    assert_eq!(metadata[2].file, "tests/metadata.rs");
+    assert_eq!(metadata[2].line, 46);
+    assert_eq!(metadata[2].module_path, "metadata::tests");
+
+    assert_eq!(metadata[0].name, "test_table_case_0");
+    assert_eq!(metadata[0].target, "table");
+    assert_eq!(metadata[0].line, 51);
+    assert_eq!(metadata[1].name, "test_table_case_1");
+    assert_eq!(metadata[1].line, 51);
+}
+
+mod tests {
+    use micro_test::test::micro_test_case;
+
+    #[micro_test_case(path = true, target = "with_path")]
+    pub fn test_with_path() {}
+
+    #[micro_test_case(target = "table")]
+    #[micro_case(1)]
+    #[micro_case(2)]
+    pub fn test_table(_num: usize) {}
+}
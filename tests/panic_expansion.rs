@@ -1,10 +1,7 @@
-use micro_test::{micro_call, report::{Reporter, set_reporter}};
+use micro_test::{micro_call, report::{Report, set_reporter}};
 use micro_test::micro_panic;
 use micro_test::panic::micro_panic_receiver;
-use micro_test::{
-    backtrace::CallStack,
-    panic::{micro_panic_relay, set_panic_handler, PanicInfo},
-};
+use micro_test::panic::{micro_panic_relay, PanicInfo};
 
 #[micro_panic_relay]
 fn target_no_input_no_output() {
@@ -39,20 +36,15 @@ fn target_input_output(mut v: Vec<usize>) -> Vec<usize> {
 #[should_panic]
 #[micro_panic_receiver]
 fn panic_test() {
-    fn handle(info: &PanicInfo) {
-        println!("{}", info.message.unwrap());
+    struct Print;
+
+    impl Report for Print {
+        fn assertion_failed(&self, info: &PanicInfo) {
+            println!("{}", info.message.unwrap());
+        }
     }
 
-    set_panic_handler(handle);
-    set_reporter(&Reporter {
-        metadata: None,
-        result: None,
-        call_stack: Some(|call_stack: CallStack| {
-            for (i, call) in call_stack.calls.iter().enumerate() {
-                println!("#{}: {}", i, call.name);
-            }
-        }),
-    });
+    set_reporter(&Print);
     let v: Vec<usize> = vec![5, 6, 7, 8];
     micro_call!(unwrap target_input_output(v));
 }
@@ -61,19 +53,14 @@ fn panic_test() {
 #[should_panic]
 #[micro_panic_receiver]
 fn recursive_panic_relay_test() {
-    //fn handle(info: &PanicInfo) {
-    //    println!("{}", info.message.unwrap());
+    //struct Print;
+
+    //impl Report for Print {
+    //    fn assertion_failed(&self, info: &PanicInfo) {
+    //        println!("{}", info.message.unwrap());
+    //    }
     //}
 
-    //set_panic_handler(handle);
-    //set_reporter(&Reporter {
-    //    metadata: None,
-    //    result: None,
-    //    call_stack: Some(|call_stack: CallStack| {
-    //        for (i, call) in call_stack.calls.iter().enumerate() {
-    //            println!("#{}: {}", i, call.name);
-    //        }
-    //    }),
-    //});
+    //set_reporter(&Print);
     micro_call!(unwrap recursive_panic_relay(10));
 }
@@ -0,0 +1,52 @@
+// `#[test_case]` functions can't be referred to by path, so this test only
+// builds without the default `custom_test_frameworks` feature, e.g. with
+// `cargo test --no-default-features --features spin_once`.
+#![cfg(not(feature = "custom_test_frameworks"))]
+
+use micro_test::runner::{self, Summary};
+use micro_test::test::TestDescriptor;
+
+fn add_by_one(num: usize) -> usize {
+    num + 1
+}
+
+static TESTS: &[TestDescriptor] = micro_test::tests![
+    tests::test_add_by_one,
+    tests::test_add_by_one_broken,
+    tests::test_add_by_one_ignored,
+];
+
+#[test]
+fn registration() {
+    assert_eq!(TESTS[0].name, "tests::test_add_by_one");
+    let mut output = String::new();
+    let summary = runner::run(TESTS, &mut output);
+    print!("{}", output);
+    assert_eq!(summary, Summary { passed: 1, failed: 1, ignored: 1, filtered_out: 0 });
+    assert!(output.starts_with("\nrunning 3 tests\n"));
+    assert!(output.contains("test add_by_one ... ok\n"));
+    assert!(output.contains("test add_by_one (broken) ... FAILED\n"));
+    assert!(output.contains("test add_by_one (ignored) ... ignored\n"));
+}
+
+mod tests {
+    use super::*;
+    use micro_test::micro_assert_eq;
+    use micro_test::test::micro_test_case;
+
+    #[micro_test_case(target = "add_by_one")]
+    pub fn test_add_by_one() {
+        micro_assert_eq!(add_by_one(1), 2);
+    }
+
+    #[micro_test_case(target = "add_by_one", feature = "broken")]
+    pub fn test_add_by_one_broken() {
+        micro_assert_eq!(add_by_one(1), 1);
+    }
+
+    #[micro_test_case(target = "add_by_one", feature = "ignored")]
+    #[micro_ignore]
+    pub fn test_add_by_one_ignored() {
+        micro_assert_eq!(add_by_one(1), 1);
+    }
+}
@@ -0,0 +1,142 @@
+use micro_test::micro_call;
+use micro_test::micro_panic;
+use micro_test::panic::micro_panic_relay;
+use micro_test::report::{set_reporter, Report};
+
+struct Quiet;
+
+impl Report for Quiet {}
+
+#[micro_panic_relay]
+fn return_in_let_initializer(x: Option<u8>) -> u8 {
+    let x = match x {
+        Some(x) => x,
+        None => return 0,
+    };
+    x + 1
+}
+
+#[micro_panic_relay]
+fn tail_in_nested_block(x: u8) -> u8 {
+    {
+        let y = x * 2;
+        {
+            let z = y + 1;
+            z.max(1)
+        }
+    }
+}
+
+#[micro_panic_relay]
+fn tail_in_if_let(x: Option<u8>) -> u8 {
+    if let Some(x) = x {
+        if x > 10 {
+            return 10;
+        }
+        x
+    } else {
+        0
+    }
+}
+
+#[micro_panic_relay]
+fn labeled_break_with_value(v: &[u8]) -> usize {
+    'found: {
+        for (i, x) in v.iter().enumerate() {
+            if *x == 0 {
+                break 'found i;
+            }
+        }
+        v.len()
+    }
+}
+
+#[micro_panic_relay]
+fn closures_and_nested_fns(x: u8) -> u8 {
+    fn double(x: u8) -> u8 {
+        if x > 50 {
+            return 100;
+        }
+        x * 2
+    }
+    let clamp = |x: u8| {
+        if x > 100 {
+            return 100;
+        }
+        x
+    };
+    #[allow(clippy::result_large_err)]
+    let checked = || -> Result<u8, micro_test::backtrace::CallStack> {
+        let x = tail_in_nested_block(x)?;
+        Ok(x)
+    };
+    clamp(double(checked()?))
+}
+
+#[micro_panic_relay]
+fn unit_return(x: u8) {
+    if x == 0 {
+        return;
+    }
+    let _ = x;
+}
+
+#[micro_panic_relay]
+fn panic_in_match_arm(x: u8) -> u8 {
+    let y = match x {
+        0 => micro_panic!("zero"),
+        x => x,
+    };
+    y
+}
+
+struct Buffer(Vec<u8>);
+
+impl Buffer {
+    #[micro_panic_relay]
+    fn first(self) -> u8 {
+        match self.0.first() {
+            Some(&first) => first,
+            None => micro_panic!("empty buffer"),
+        }
+    }
+}
+
+fn make_buffer(v: Vec<u8>) -> Buffer {
+    Buffer(v)
+}
+
+#[micro_panic_relay]
+fn question_mark_on_moving_receiver(v: Vec<u8>) -> u8 {
+    make_buffer(v).first()?
+}
+
+// A single expression body must not trip `unused_braces` once wrapped.
+#[deny(unused_braces)]
+#[micro_panic_relay]
+fn single_expression(x: u8) -> u8 {
+    x
+}
+
+#[test]
+fn relay_rewrite_handles_every_position() {
+    assert_eq!(micro_call!(unwrap return_in_let_initializer(None)), 0);
+    assert_eq!(micro_call!(unwrap return_in_let_initializer(Some(1))), 2);
+    assert_eq!(micro_call!(unwrap tail_in_nested_block(2)), 5);
+    assert_eq!(micro_call!(unwrap single_expression(4)), 4);
+    assert_eq!(micro_call!(unwrap tail_in_if_let(Some(11))), 10);
+    assert_eq!(micro_call!(unwrap tail_in_if_let(Some(3))), 3);
+    assert_eq!(micro_call!(unwrap tail_in_if_let(None)), 0);
+    assert_eq!(micro_call!(unwrap labeled_break_with_value(&[1, 0, 2])), 1);
+    assert_eq!(micro_call!(unwrap labeled_break_with_value(&[1, 2])), 2);
+    assert_eq!(micro_call!(unwrap closures_and_nested_fns(3)), 14);
+    assert_eq!(micro_call!(unwrap closures_and_nested_fns(30)), 100);
+    micro_call!(unwrap unit_return(0));
+    micro_call!(unwrap unit_return(1));
+
+    set_reporter(&Quiet);
+    assert_eq!(micro_call!(unwrap panic_in_match_arm(1)), 1);
+    assert!(micro_call!(result panic_in_match_arm(0)).is_err());
+    assert_eq!(micro_call!(unwrap question_mark_on_moving_receiver(vec![7])), 7);
+    assert!(micro_call!(result question_mark_on_moving_receiver(Vec::new())).is_err());
+}
@@ -0,0 +1,74 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use micro_test::micro_call;
+use micro_test::micro_panic;
+use micro_test::panic::{micro_panic_relay, PanicInfo};
+use micro_test::report::{self, set_reporter, FanOut, Report, TestOutcome};
+use micro_test::runner::Summary;
+use micro_test::test::{self, Metadata};
+
+struct Counter {
+    events: AtomicUsize,
+}
+
+impl Report for Counter {
+    fn run_started(&self, _test_count: usize) {
+        self.events.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn test_started(&self, _metadata: &Metadata) {
+        self.events.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn assertion_failed(&self, _info: &PanicInfo) {
+        self.events.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn test_finished(&self, _metadata: &Metadata, _outcome: &TestOutcome) {
+        self.events.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn run_finished(&self, _summary: &Summary) {
+        self.events.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+struct Log {
+    lines: Mutex<Vec<String>>,
+}
+
+impl Report for Log {
+    fn test_started(&self, metadata: &Metadata) {
+        self.lines.lock().unwrap().push(format!("started {}", metadata));
+    }
+
+    fn assertion_failed(&self, info: &PanicInfo) {
+        self.lines.lock().unwrap().push(format!("failed {}", info.message.unwrap()));
+    }
+}
+
+static COUNTER: Counter = Counter { events: AtomicUsize::new(0) };
+static LOG: Log = Log { lines: Mutex::new(Vec::new()) };
+static REPORTER: FanOut = FanOut::new(&[&COUNTER, &LOG]);
+
+#[micro_panic_relay]
+fn failing() {
+    micro_panic!("boom {}", 1);
+}
+
+#[test]
+fn fan_out_forwards_to_every_reporter() {
+    set_reporter(&REPORTER);
+    assert_eq!(REPORTER.reporters().len(), 2);
+
+    let metadata = Metadata { target: "uart", feature: Some("loopback"), ..Metadata::new() };
+    report::reporter().run_started(1);
+    test::report_metadata(&metadata);
+    let call_stack = micro_call!(result failing()).unwrap_err();
+    report::reporter().test_finished(&metadata, &TestOutcome::Failed { call_stack: Some(&call_stack), note: None });
+    report::reporter().run_finished(&Summary { failed: 1, ..Summary::default() });
+
+    assert_eq!(COUNTER.events.load(Ordering::Relaxed), 5);
+    assert_eq!(*LOG.lines.lock().unwrap(), ["started uart (loopback)", "failed boom 1"]);
+}
@@ -0,0 +1,135 @@
+#![feature(custom_test_frameworks)]
+#![test_runner(test_runner)]
+
+use micro_test::backtrace::CallStack;
+use micro_test::runner::{self, Summary};
+
+fn add_by_one(num: usize) -> usize {
+    num + 1
+}
+
+fn test_runner(tests: &[&dyn Fn() -> Result<(), CallStack>]) {
+    let mut output = String::new();
+    let summary = runner::run(tests, &mut output);
+    print!("{}", output);
+    assert_eq!(summary, Summary { passed: 7, failed: 7, ignored: 2, filtered_out: 0 });
+    assert!(output.starts_with("\nrunning 16 tests\n"));
+    assert!(output.contains("test add_by_one (return value) ... ok\n"));
+    assert!(output.contains("test add_by_one (broken) ... FAILED\n    assertion failed: `(left == right)`"));
+    assert!(output.contains("test add_by_one (flash) ... ignored, needs external flash\n"));
+    assert!(output.contains("test add_by_one (ignored) ... ignored\n"));
+    assert!(output.contains("test add_by_one (should fail) ... ok\n"));
+    assert!(output.contains("test add_by_one (should fail with message) ... ok\n"));
+    assert!(output.contains("test add_by_one (should fail with other message) ... FAILED\n"));
+    assert!(output.contains("    note: panic did not contain expected string `overflow`\n"));
+    assert!(output.contains(
+        "test add_by_one (should fail then fails otherwise) ... FAILED\n    add_by_one: not an identity: tests/runner.rs:98:9\n"
+    ));
+    assert!(output.contains("test add_by_one (should fail but passes) ... FAILED\n    note: test did not fail as expected\n"));
+    assert!(output.contains("test add_by_one (table: 0, 1) ... ok\n"));
+    assert!(output.contains("test add_by_one (table: 41, 42) ... ok\n"));
+    assert!(output.contains("test add_by_one (table: 1, 1) ... FAILED\n"));
+    assert!(output.contains("    #0: test_add_by_one_table at tests/runner.rs:107:5\n"));
+    assert!(output.contains("test add_by_one (expectations) ... ok\n"));
+    assert!(output.contains(concat!(
+        "test add_by_one (broken expectations) ... FAILED\n",
+        "    expectation failed: `add_by_one(0) == 0`: tests/runner.rs:",
+    )));
+    assert!(output.contains("    expectation failed: `(left == right)`\n left: `3`,\nright: `4`: second: tests/runner.rs:"));
+    assert!(output.contains("    expectation failed: `(left != right)`\n left: `4`,\nright: `4`: tests/runner.rs:"));
+    assert!(output.contains("test add_by_one (expectation before return) ... FAILED\n"));
+    assert!(output.ends_with("\ntest result: FAILED. 7 passed; 7 failed; 2 ignored; 0 filtered out\n"));
+}
+
+mod tests {
+    use super::*;
+    use micro_test::{micro_assert, micro_assert_eq, micro_expect, micro_expect_eq, micro_expect_ne};
+    use micro_test::test::micro_test_case;
+
+    #[micro_test_case]
+    pub fn test_add_by_one() {
+        micro_assert_eq!(add_by_one(1), 2);
+    }
+
+    #[micro_test_case(target = "add_by_one", feature = "return value")]
+    pub fn test_add_by_one_return_value() {
+        micro_assert_eq!(add_by_one(2), 3);
+    }
+
+    #[micro_test_case(target = "add_by_one", feature = "broken")]
+    pub fn test_add_by_one_broken() {
+        micro_assert_eq!(add_by_one(2), 2);
+    }
+
+    #[micro_test_case(target = "add_by_one", feature = "ignored")]
+    #[micro_ignore]
+    pub fn test_add_by_one_ignored() {
+        micro_assert_eq!(add_by_one(2), 2);
+    }
+
+    #[micro_test_case(target = "add_by_one", feature = "flash")]
+    #[micro_ignore = "needs external flash"]
+    pub fn test_add_by_one_flash() {
+        micro_assert_eq!(add_by_one(2), 2);
+    }
+
+    #[micro_test_case(target = "add_by_one", feature = "should fail")]
+    #[micro_should_fail]
+    pub fn test_add_by_one_should_fail() {
+        micro_assert_eq!(add_by_one(2), 2);
+    }
+
+    #[micro_test_case(target = "add_by_one", feature = "should fail with message")]
+    #[micro_should_fail(expected = "overflow")]
+    pub fn test_add_by_one_should_fail_with_message() {
+        micro_assert!(add_by_one(usize::MAX - 1) < usize::MAX, "add_by_one: overflow at {}", usize::MAX - 1);
+    }
+
+    #[micro_test_case(target = "add_by_one", feature = "should fail with other message")]
+    #[micro_should_fail(expected = "overflow")]
+    pub fn test_add_by_one_should_fail_with_other_message() {
+        micro_assert!(add_by_one(1) == 1, "add_by_one: not an identity");
+    }
+
+    #[micro_test_case(target = "add_by_one", feature = "should fail then fails otherwise")]
+    #[micro_should_fail(expected = "overflow")]
+    pub fn test_add_by_one_should_fail_then_fails_otherwise() {
+        micro_expect!(add_by_one(usize::MAX - 1) < usize::MAX, "add_by_one: overflow");
+        micro_assert!(add_by_one(1) == 1, "add_by_one: not an identity");
+    }
+
+    #[micro_test_case(target = "add_by_one", feature = "should fail but passes")]
+    #[micro_should_fail]
+    pub fn test_add_by_one_should_fail_but_passes() {
+        micro_assert_eq!(add_by_one(1), 2);
+    }
+
+    #[micro_test_case(target = "add_by_one", feature = "table")]
+    #[micro_case(0, 1)]
+    #[micro_case(41, 42)]
+    #[micro_case(1, 1)]
+    pub fn test_add_by_one_table(num: usize, expected: usize) {
+        micro_assert_eq!(add_by_one(num), expected);
+    }
+
+    #[micro_test_case(target = "add_by_one", feature = "expectations")]
+    pub fn test_add_by_one_expectations() {
+        micro_expect!(add_by_one(0) == 1);
+        micro_expect_eq!(add_by_one(1), 2);
+        micro_expect_ne!(add_by_one(2), 2, "identity");
+    }
+
+    #[micro_test_case(target = "add_by_one", feature = "broken expectations")]
+    pub fn test_add_by_one_broken_expectations() {
+        micro_expect!(add_by_one(0) == 0);
+        micro_expect_eq!(add_by_one(2), 4, "second");
+        micro_expect_ne!(add_by_one(3), 4);
+    }
+
+    #[micro_test_case(target = "add_by_one", feature = "expectation before return")]
+    #[allow(clippy::needless_return)]
+    pub fn test_add_by_one_expectation_before_return() {
+        micro_expect!(false);
+        return;
+    }
+}
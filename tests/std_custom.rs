@@ -1,36 +1,38 @@
 #![feature(custom_test_frameworks)]
-#![feature(trace_macros)]
 #![test_runner(test_runner)]
 
 fn add_by_one(num: usize) -> usize {
     num + 1
 }
 
-fn report_metadata(metadata: &micro_test::test::Metadata) {
-    match metadata.feature {
-        Some(feature) => {
-            print!("test {} ({}) ... ", metadata.target, feature);
-        }
-        None => {
-            print!("test {} ... ", metadata.target);
+struct Console;
+
+impl micro_test::report::Report for Console {
+    fn test_started(&self, metadata: &micro_test::test::Metadata) {
+        match metadata.feature {
+            Some(feature) => {
+                print!("test {} ({}) ... ", metadata.target, feature);
+            }
+            None => {
+                print!("test {} ... ", metadata.target);
+            }
         }
     }
-}
 
-fn panic_handler(info: &micro_test::panic::PanicInfo) {
-    println!("FAILED: {}", info);
+    fn assertion_failed(&self, info: &micro_test::panic::PanicInfo) {
+        println!("FAILED: {}", info);
+    }
 }
 
 fn test_runner(tests: &[&dyn Fn() -> core::result::Result<(), micro_test::backtrace::CallStack>]) {
-    micro_test::test::set_metadata_reporter(report_metadata);
-    micro_test::panic::set_panic_handler(panic_handler);
+    micro_test::report::set_reporter(&Console);
     println!(r#"
 running {} tests"#, tests.len());
     for test in tests {
         match test() {
             Ok(_) => println!("ok"),
             Err(call_stack) => {
-                for (i, func_call) in call_stack.calls.iter().enumerate() {
+                for (i, func_call) in call_stack.calls().iter().enumerate() {
                     println!("{}: {}", i, func_call.name);
                 }
             }
@@ -0,0 +1,64 @@
+#![cfg(feature = "std")]
+#![feature(custom_test_frameworks)]
+#![test_runner(test_runner)]
+
+use micro_test::backtrace::CallStack;
+use micro_test::runner::{self, Summary};
+
+fn test_runner(tests: &[&dyn Fn() -> Result<(), CallStack>]) {
+    let mut output = String::new();
+    let summary = runner::run(tests, &mut output);
+    print!("{}", output);
+    assert_eq!(summary, Summary { passed: 4, failed: 3, ignored: 0, filtered_out: 0 });
+    assert!(output.contains("test real_panic (panic) ... FAILED\n    real panic 42: tests/std_panic.rs:"));
+    assert!(output.contains("test real_panic (unwrap) ... FAILED\n    called `Option::unwrap()` on a `None` value: tests/std_panic.rs:"));
+    assert!(output.contains("test real_panic (index) ... FAILED\n    index out of bounds: the len is 3 but the index is 3: tests/std_panic.rs:"));
+    assert!(output.contains("test real_panic (should fail) ... ok\n"));
+    assert!(output.contains("test real_panic (should fail without expected) ... ok\n"));
+    assert!(output.contains("test real_panic (after panics) ... ok\n"));
+    assert!(output.contains("test real_panic (after panics with assertion) ... ok\n"));
+}
+
+mod tests {
+    use micro_test::micro_assert_eq;
+    use micro_test::test::micro_test_case;
+
+    #[micro_test_case(target = "real_panic", feature = "panic")]
+    pub fn test_panic() {
+        panic!("real panic {}", 42);
+    }
+
+    #[micro_test_case(target = "real_panic", feature = "unwrap")]
+    pub fn test_unwrap() {
+        let position = [1, 2, 3].iter().position(|&x| x == 4);
+        position.unwrap();
+    }
+
+    #[micro_test_case(target = "real_panic", feature = "index")]
+    pub fn test_index() {
+        let v = [1, 2, 3];
+        let i = v.len();
+        let _ = v[i];
+    }
+
+    #[micro_test_case(target = "real_panic", feature = "should fail")]
+    #[micro_should_fail(expected = "overflow")]
+    pub fn test_should_fail() {
+        panic!("overflow");
+    }
+
+    #[micro_test_case(target = "real_panic", feature = "should fail without expected")]
+    #[micro_should_fail]
+    pub fn test_should_fail_without_expected() {
+        let v: [u8; 0] = [];
+        let _ = v.iter().max().unwrap();
+    }
+
+    #[micro_test_case(target = "real_panic", feature = "after panics")]
+    pub fn test_after_panics() {}
+
+    #[micro_test_case(target = "real_panic", feature = "after panics with assertion")]
+    pub fn test_after_panics_with_assertion() {
+        micro_assert_eq!(1 + 1, 2);
+    }
+}
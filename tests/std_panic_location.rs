@@ -0,0 +1,48 @@
+#![cfg(feature = "std")]
+#![feature(custom_test_frameworks)]
+#![test_runner(test_runner)]
+
+use std::sync::Mutex;
+
+use micro_test::backtrace::CallStack;
+use micro_test::report::{Report, TestOutcome};
+use micro_test::runner::{self, Filter, Summary};
+use micro_test::test::Metadata;
+
+/// Records where the call stack of each failed test is located.
+struct Locations(Mutex<Vec<Option<(&'static str, u32)>>>);
+
+impl Report for Locations {
+    fn test_finished(&self, _metadata: &Metadata, outcome: &TestOutcome) {
+        if let TestOutcome::Failed { call_stack: Some(call_stack), .. } = outcome {
+            let location = call_stack.location().map(|location| (location.file(), location.line()));
+            self.0.lock().unwrap().push(location);
+        }
+    }
+}
+
+static LOCATIONS: Locations = Locations(Mutex::new(Vec::new()));
+
+fn test_runner(tests: &[&dyn Fn() -> Result<(), CallStack>]) {
+    let summary = runner::run_with_reporter(tests, Filter::Substring(""), &LOCATIONS);
+    assert_eq!(summary, Summary { passed: 0, failed: 2, ignored: 0, filtered_out: 0 });
+    assert_eq!(
+        *LOCATIONS.0.lock().unwrap(),
+        [Some(("tests/std_panic_location.rs", 40)), Some(("tests/std_panic_location.rs", 46))]
+    );
+}
+
+mod tests {
+    use micro_test::test::micro_test_case;
+
+    #[micro_test_case(target = "real_panic", feature = "panic")]
+    pub fn test_panic() {
+        panic!("real panic");
+    }
+
+    #[micro_test_case(target = "real_panic", feature = "unwrap")]
+    pub fn test_unwrap() {
+        let position = [1, 2, 3].iter().position(|&x| x == 4);
+        position.unwrap();
+    }
+}
@@ -0,0 +1,48 @@
+#![cfg(feature = "std")]
+#![feature(custom_test_frameworks)]
+#![test_runner(test_runner)]
+
+use core::fmt::{Result as FmtResult, Write};
+
+use micro_test::backtrace::CallStack;
+use micro_test::report::Libtest;
+use micro_test::runner::{self, Filter, Summary};
+
+/// Panics the first time the name of a test is written into it.
+struct PanickingSink {
+    output: String,
+    panicked: bool,
+}
+
+impl Write for PanickingSink {
+    fn write_str(&mut self, s: &str) -> FmtResult {
+        if !self.panicked && s.contains("test_a_sink_panics") {
+            self.panicked = true;
+            panic!("sink failed");
+        }
+        self.output.write_str(s)
+    }
+}
+
+static LIBTEST: Libtest<PanickingSink> = Libtest::new(PanickingSink { output: String::new(), panicked: false });
+
+fn test_runner(tests: &[&dyn Fn() -> Result<(), CallStack>]) {
+    // The panic of the sink is reported while the reporter is locked, which
+    // must not deadlock.
+    let summary = runner::run_with_reporter(tests, Filter::Substring(""), &LIBTEST);
+    assert_eq!(summary, Summary { passed: 1, failed: 1, ignored: 0, filtered_out: 0 });
+    LIBTEST.with_sink(|sink| {
+        assert!(sink.output.contains("FAILED\n"));
+        assert!(sink.output.contains("test tests::test_b_pass ... ok\n"));
+    });
+}
+
+mod tests {
+    use micro_test::test::micro_test_case;
+
+    #[micro_test_case]
+    pub fn test_a_sink_panics() {}
+
+    #[micro_test_case]
+    pub fn test_b_pass() {}
+}
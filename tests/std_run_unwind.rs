@@ -0,0 +1,39 @@
+#![cfg(feature = "std")]
+#![feature(custom_test_frameworks)]
+#![test_runner(test_runner)]
+
+use core::fmt::{Result as FmtResult, Write};
+
+use micro_test::backtrace::CallStack;
+use micro_test::runner;
+
+/// Panics when the outcome of a test is written into it.
+struct PanickingSink {
+    output: String,
+}
+
+impl Write for PanickingSink {
+    fn write_str(&mut self, s: &str) -> FmtResult {
+        if s.contains("ok") {
+            panic!("sink failed");
+        }
+        self.output.write_str(s)
+    }
+}
+
+fn test_runner(tests: &[&dyn Fn() -> Result<(), CallStack>]) {
+    let mut sink = PanickingSink { output: String::new() };
+    let unwound = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| runner::run(tests, &mut sink)));
+    assert!(unwound.is_err());
+    // The run is over, so a later panic must not reach its output.
+    let _ = std::panic::catch_unwind(|| panic!("after the run"));
+    assert!(sink.output.contains("test test_pass ... "));
+    assert!(!sink.output.contains("after the run"));
+}
+
+mod tests {
+    use micro_test::test::micro_test_case;
+
+    #[micro_test_case]
+    pub fn test_pass() {}
+}
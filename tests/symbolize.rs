@@ -0,0 +1,42 @@
+#![cfg(all(feature = "std", target_os = "linux"))]
+
+use micro_test::symbolize::Symbolizer;
+
+#[inline(never)]
+fn symbolized() -> usize {
+    symbolized as *const () as usize
+}
+
+/// Returns the address this test executable has been loaded at, which is the
+/// bias of a position-independent executable.
+fn load_address() -> u64 {
+    let exe = std::env::current_exe().unwrap();
+    let maps = std::fs::read_to_string("/proc/self/maps").unwrap();
+    let line = maps
+        .lines()
+        .find(|line| line.ends_with(exe.to_str().unwrap()))
+        .unwrap();
+    u64::from_str_radix(line.split('-').next().unwrap(), 16).unwrap()
+}
+
+#[test]
+fn symbolizer_rewrites_backtrace_lines() {
+    let elf = std::fs::read(std::env::current_exe().unwrap()).unwrap();
+    let symbolizer = Symbolizer::with_bias(&elf, load_address()).unwrap();
+
+    // Pretends that the first instruction of `symbolized` is a return address.
+    let address = symbolized() + 1;
+    let symbol = symbolizer.resolve(address as u64).unwrap();
+    assert_eq!(symbol.function.as_deref(), Some("symbolize::symbolized"));
+    assert!(symbol.file.unwrap().ends_with("tests/symbolize.rs"));
+    assert!((6..=8).contains(&symbol.line.unwrap()));
+
+    let line = format!("      #0: {:#018x}", address);
+    assert!(symbolizer
+        .symbolize_line(&line)
+        .starts_with("      #0: symbolize::symbolized ("));
+    assert!(symbolizer.symbolize_line(&line).contains("tests/symbolize.rs:"));
+
+    assert_eq!(symbolizer.symbolize_line("    backtrace:"), "    backtrace:");
+    assert_eq!(symbolizer.symbolize_line("#1: 0x0"), "#1: 0x0");
+}
@@ -0,0 +1,84 @@
+#![feature(custom_test_frameworks)]
+#![test_runner(test_runner)]
+
+use micro_test::backtrace::CallStack;
+use micro_test::report::Tap;
+use micro_test::runner::{self, Filter, Summary};
+
+static TAP: Tap<String> = Tap::new(String::new());
+
+fn test_runner(tests: &[&dyn Fn() -> Result<(), CallStack>]) {
+    let summary = runner::run_with_reporter(tests, Filter::new("uart"), &TAP);
+    assert_eq!(summary, Summary { passed: 1, failed: 3, ignored: 1, filtered_out: 1 });
+    TAP.with_sink(|output| {
+        assert_eq!(
+            output,
+            concat!(
+                "TAP version 13\n",
+                "ok 1 - uart (loopback)\n",
+                "not ok 2 - uart (baud rate)\n",
+                "  ---\n",
+                "  message: \"baud is \\\"9600\\\"\"\n",
+                "  severity: fail\n",
+                "  at:\n",
+                "    file: \"tests/tap.rs\"\n",
+                "    line: 66\n",
+                "  ...\n",
+                "ok 3 - uart (\\#2) # SKIP needs hardware\n",
+                "not ok 4 - uart (relay)\n",
+                "  ---\n",
+                "  message: \"not ready\"\n",
+                "  severity: fail\n",
+                "  at:\n",
+                "    file: \"tests/tap.rs\"\n",
+                "    line: 52\n",
+                "  stack:\n",
+                "    - \"super::check(false) at tests/tap.rs:78:9\"\n",
+                "  ...\n",
+                "not ok 5 - uart (should fail)\n",
+                "  ---\n",
+                "  message: \"test did not fail as expected\"\n",
+                "  severity: fail\n",
+                "  ...\n",
+                "1..5\n",
+            )
+        );
+    });
+}
+
+#[micro_test::panic::micro_panic_relay]
+fn check(ready: bool) {
+    if !ready {
+        micro_test::micro_panic!("not ready");
+    }
+}
+
+mod tests {
+    use micro_test::{micro_assert, micro_call};
+    use micro_test::test::micro_test_case;
+
+    #[micro_test_case(target = "uart", feature = "loopback")]
+    pub fn test_a_pass() {}
+
+    #[micro_test_case(target = "uart", feature = "baud rate")]
+    pub fn test_b_assert() {
+        let baud = 9600;
+        micro_assert!(baud == 115200, "baud is \"{}\"", baud);
+    }
+
+    #[micro_test_case(target = "uart", feature = "#2")]
+    #[micro_ignore = "needs hardware"]
+    pub fn test_c_ignored() {}
+
+    #[micro_test_case(target = "flash", feature = "erase")]
+    pub fn test_d_filtered_out() {}
+
+    #[micro_test_case(target = "uart", feature = "relay")]
+    pub fn test_e_relay() {
+        micro_call!(relay_debug super::check(false));
+    }
+
+    #[micro_test_case(target = "uart", feature = "should fail")]
+    #[micro_should_fail]
+    pub fn test_f_should_fail() {}
+}